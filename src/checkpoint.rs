@@ -0,0 +1,181 @@
+use std::{
+    fs,
+    io::{Error, ErrorKind},
+    path::{Path, PathBuf},
+};
+
+use nalgebra::{DMatrix, DVector};
+
+use crate::{complex::Complex, scenario::Scenario};
+
+const MAGIC: &[u8; 8] = b"QPCHKPT\0";
+const VERSION: u32 = 1;
+
+// The full state of a run, enough to pick it up again later. Stored as
+//
+// magic (8 bytes) and version (u32)
+// number of dimensions (u8), step (u64) and time (f64)
+// length of the scenario (u64) followed by the scenario as toml
+// rows and columns of psi (u64 each) followed by re and im of every value, row by row
+//
+// with every number in little endian. The scenario holds the grid, the potential and the
+// integrator, so a checkpoint can be resumed without the files it was started from.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Checkpoint {
+    pub dims: u8,
+    pub step: usize,
+    pub time: f64,
+    pub scenario: Scenario,
+    shape: (usize, usize),
+    psi: Vec<Complex>,
+}
+
+impl Checkpoint {
+    pub fn from_1d(step: usize, time: f64, scenario: &Scenario, psi: &DVector<Complex>) -> Self {
+        Self {
+            dims: 1,
+            step,
+            time,
+            scenario: scenario.clone(),
+            shape: (psi.len(), 1),
+            psi: psi.iter().copied().collect(),
+        }
+    }
+
+    pub fn from_2d(step: usize, time: f64, scenario: &Scenario, psi: &DMatrix<Complex>) -> Self {
+        Self {
+            dims: 2,
+            step,
+            time,
+            scenario: scenario.clone(),
+            shape: psi.shape(),
+            psi: psi.transpose().iter().copied().collect(),
+        }
+    }
+
+    pub fn psi_1d(&self) -> DVector<Complex> {
+        DVector::from(self.psi.clone())
+    }
+
+    pub fn psi_2d(&self) -> DMatrix<Complex> {
+        DMatrix::from_row_slice(self.shape.0, self.shape.1, &self.psi)
+    }
+
+    // writes to a temporary file first, so a run that is killed while saving
+    // still leaves the previous checkpoint behind
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), Error> {
+        let path = path.as_ref();
+        let scenario = toml::to_string(&self.scenario)
+            .map_err(|e| Error::new(ErrorKind::InvalidData, e.to_string()))?;
+
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend(VERSION.to_le_bytes());
+        bytes.push(self.dims);
+        bytes.extend((self.step as u64).to_le_bytes());
+        bytes.extend(self.time.to_le_bytes());
+        bytes.extend((scenario.len() as u64).to_le_bytes());
+        bytes.extend(scenario.as_bytes());
+        bytes.extend((self.shape.0 as u64).to_le_bytes());
+        bytes.extend((self.shape.1 as u64).to_le_bytes());
+        for p in &self.psi {
+            bytes.extend(p.real().to_le_bytes());
+            bytes.extend(p.imag().to_le_bytes());
+        }
+
+        // .tmp goes after the whole name, so that a.chk and a.bin don't share a.tmp and
+        // saving to run.tmp still writes somewhere else first
+        let mut temporary = path.as_os_str().to_owned();
+        temporary.push(".tmp");
+        let temporary = PathBuf::from(temporary);
+        fs::write(&temporary, bytes)
+            .and_then(|_| fs::rename(&temporary, path))
+            .map_err(|e| {
+                Error::new(
+                    e.kind(),
+                    format!("Failed to write checkpoint {}: {e}", path.display()),
+                )
+            })
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let bytes = fs::read(path).map_err(|e| {
+            Error::new(
+                e.kind(),
+                format!("Failed to read checkpoint {}: {e}", path.display()),
+            )
+        })?;
+        Self::parse(&bytes).map_err(|e| {
+            Error::new(
+                ErrorKind::InvalidData,
+                format!("Invalid checkpoint {}: {e}", path.display()),
+            )
+        })
+    }
+
+    fn parse(bytes: &[u8]) -> Result<Self, String> {
+        let mut reader = Reader { bytes, at: 0 };
+        if reader.take(MAGIC.len())? != MAGIC {
+            return Err("not a checkpoint".to_string());
+        }
+        let version = u32::from_le_bytes(reader.array()?);
+        if version != VERSION {
+            return Err(format!("unsupported version {version}"));
+        }
+        let dims = reader.take(1)?[0];
+        let step = u64::from_le_bytes(reader.array()?) as usize;
+        let time = f64::from_le_bytes(reader.array()?);
+        let length = u64::from_le_bytes(reader.array()?) as usize;
+        let scenario = std::str::from_utf8(reader.take(length)?)
+            .map_err(|e| e.to_string())
+            .and_then(|content| Scenario::parse(content, dims).map_err(|e| e.to_string()))?;
+        let rows = u64::from_le_bytes(reader.array()?) as usize;
+        let cols = u64::from_le_bytes(reader.array()?) as usize;
+
+        let n = scenario.grid.points().len();
+        if (dims == 1 && (rows, cols) != (n, 1)) || (dims == 2 && (rows, cols) != (n, n)) {
+            return Err(format!(
+                "the wave function has {rows}x{cols} values, which doesn't fit the grid"
+            ));
+        }
+        let psi = (0..rows * cols)
+            .map(|_| {
+                let re = f64::from_le_bytes(reader.array()?);
+                let im = f64::from_le_bytes(reader.array()?);
+                Ok(Complex::new(re, im))
+            })
+            .collect::<Result<Vec<Complex>, String>>()?;
+        if reader.at != bytes.len() {
+            return Err("unexpected data after the wave function".to_string());
+        }
+
+        Ok(Self {
+            dims,
+            step,
+            time,
+            scenario,
+            shape: (rows, cols),
+            psi,
+        })
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    at: usize,
+}
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> Result<&'a [u8], String> {
+        let res = self
+            .at
+            .checked_add(n)
+            .and_then(|end| self.bytes.get(self.at..end))
+            .ok_or("the file ends too early")?;
+        self.at += n;
+        Ok(res)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], String> {
+        Ok(self.take(N)?.try_into().unwrap())
+    }
+}
@@ -1,19 +1,51 @@
 use std::{
-    f64::consts::E,
-    fmt::Display,
-    ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Sub, SubAssign},
+    error::Error,
+    fmt::{Debug, Display},
+    iter::{Product, Sum},
+    ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Neg, Rem, Sub, SubAssign},
+    str::FromStr,
 };
 
 use nalgebra::{base::dimension::Dyn, base::VecStorage, Const, Matrix};
-use num_traits::identities::{One, Zero};
+use num_traits::{
+    identities::{One, Zero},
+    Float, FloatConst, Num,
+};
 
+// The floating point types a Complex can be made of, i.e. f32 and f64. Everything in the
+// scenario is f64, so values are converted with of() when running in f32.
+pub trait Real:
+    Float + FloatConst + Debug + Display + FromStr + Default + Send + Sync + 'static
+{
+    // value as Self, rounded to the nearest f32 if Self is f32
+    fn of(value: f64) -> Self;
+    fn as_f64(self) -> f64;
+}
+impl Real for f32 {
+    fn of(value: f64) -> Self {
+        value as f32
+    }
+    fn as_f64(self) -> f64 {
+        self as f64
+    }
+}
+impl Real for f64 {
+    fn of(value: f64) -> Self {
+        value
+    }
+    fn as_f64(self) -> f64 {
+        self
+    }
+}
+
+// Complex on its own means Complex<f64>, which is what the simulation uses unless asked otherwise
 #[derive(Debug, Clone, Copy, PartialEq)]
-pub struct Complex {
-    re: f64,
-    im: f64,
+pub struct Complex<T = f64> {
+    re: T,
+    im: T,
 }
-impl Complex {
-    pub fn new(re: f64, im: f64) -> Self {
+impl<T: Real> Complex<T> {
+    pub fn new(re: T, im: T) -> Self {
         Self { re, im }
     }
     pub fn complex_conjugate(&self) -> Self {
@@ -23,46 +55,311 @@ impl Complex {
         }
     }
 
-    pub fn from_real(value: f64) -> Self {
-        Self { re: value, im: 0. }
+    pub fn i() -> Self {
+        Self {
+            re: T::zero(),
+            im: T::one(),
+        }
+    }
+
+    pub fn from_real(value: T) -> Self {
+        Self {
+            re: value,
+            im: T::zero(),
+        }
     }
 
-    pub fn real(&self) -> f64 {
+    pub fn real(&self) -> T {
         self.re
     }
-    pub fn imag(&self) -> f64 {
+    pub fn imag(&self) -> T {
         self.im
     }
 
-    pub fn abs_squared(&self) -> f64 {
+    // the same number in another precision, e.g. Complex<f64> to Complex<f32>
+    pub fn cast<U: Real>(self) -> Complex<U> {
+        Complex::new(U::of(self.re.as_f64()), U::of(self.im.as_f64()))
+    }
+
+    // r*e^(i*theta)
+    pub fn from_polar(r: T, theta: T) -> Self {
+        Self {
+            re: r * theta.cos(),
+            im: r * theta.sin(),
+        }
+    }
+    // (|z|, arg(z))
+    pub fn to_polar(&self) -> (T, T) {
+        (self.abs(), self.arg())
+    }
+
+    pub fn abs_squared(&self) -> T {
         self.re.powi(2) + self.im.powi(2)
     }
+    // |z|, without overflowing for large parts
+    pub fn abs(&self) -> T {
+        self.re.hypot(self.im)
+    }
+    // the angle to the positive real axis, in (-pi, pi]
+    pub fn arg(&self) -> T {
+        self.im.atan2(self.re)
+    }
+    pub fn recip(&self) -> Self {
+        Self::one() / *self
+    }
+
+    pub fn is_nan(&self) -> bool {
+        self.re.is_nan() || self.im.is_nan()
+    }
+    pub fn is_finite(&self) -> bool {
+        self.re.is_finite() && self.im.is_finite()
+    }
+
+    // can be called as z.exp() as well as Complex::exp(z)
+    pub fn exp(self) -> Self {
+        Self::from_polar(self.re.exp(), self.im)
+    }
+    // the principal branch, with the imaginary part in (-pi, pi]
+    pub fn ln(self) -> Self {
+        Self::new(self.abs().ln(), self.arg())
+    }
+    pub fn log(self, base: T) -> Self {
+        self.ln() / base.ln()
+    }
+    pub fn log10(self) -> Self {
+        self.log(T::of(10.))
+    }
 
-    pub fn exp(input: Self) -> Self {
-        E.powf(input.re)
-            * Self {
-                re: (input.imag()).cos(),
-                im: (input.imag()).sin(),
+    // The principal root, with a non-negative real part. Computed from the parts directly,
+    // so that e.g. the root of -4 is exactly 2i.
+    pub fn sqrt(self) -> Self {
+        if self.is_zero() {
+            return Self::zero();
+        }
+        let (r, two) = (self.abs(), T::of(2.));
+        Self {
+            re: ((r + self.re) / two).sqrt(),
+            im: ((r - self.re) / two).sqrt().copysign(self.im),
+        }
+    }
+    // the principal cube root
+    pub fn cbrt(self) -> Self {
+        self.powf(T::of(1. / 3.))
+    }
+
+    // by repeated squaring, so small integer powers are as exact as multiplying by hand
+    pub fn powi(self, n: i32) -> Self {
+        let mut res = Self::one();
+        let mut base = self;
+        let mut exp = n.unsigned_abs();
+        while exp > 0 {
+            if exp & 1 == 1 {
+                res *= base;
             }
+            base *= base;
+            exp >>= 1;
+        }
+        if n < 0 {
+            res.recip()
+        } else {
+            res
+        }
+    }
+    pub fn powf(self, exp: T) -> Self {
+        if self.is_zero() {
+            return if exp.is_zero() {
+                Self::one()
+            } else {
+                Self::zero()
+            };
+        }
+        let (r, theta) = self.to_polar();
+        Self::from_polar(r.powf(exp), theta * exp)
+    }
+    // z^w = e^(w*ln(z)), with 0^0 = 1 and 0^w = 0 otherwise
+    pub fn powc(self, exp: Self) -> Self {
+        if self.is_zero() {
+            return if exp.is_zero() {
+                Self::one()
+            } else {
+                Self::zero()
+            };
+        }
+        (exp * self.ln()).exp()
+    }
+
+    // trigonometric functions, written out in the real and imaginary parts
+    pub fn sin(self) -> Self {
+        Self::new(
+            self.re.sin() * self.im.cosh(),
+            self.re.cos() * self.im.sinh(),
+        )
+    }
+    pub fn cos(self) -> Self {
+        Self::new(
+            self.re.cos() * self.im.cosh(),
+            -self.re.sin() * self.im.sinh(),
+        )
+    }
+    // (sin(2a) + i*sinh(2b)) / (cos(2a) + cosh(2b)), with the imaginary part divided through
+    // by cosh(2b) so that it goes to +-1 rather than inf/inf for large b
+    pub fn tan(self) -> Self {
+        let two = T::of(2.);
+        let (a, b) = (two * self.re, two * self.im);
+        Self::new(
+            a.sin() / (a.cos() + b.cosh()),
+            b.tanh() / (T::one() + a.cos() / b.cosh()),
+        )
+    }
+    // asin(z) = -i*ln(iz + sqrt(1 - z^2))
+    pub fn asin(self) -> Self {
+        -Self::i() * (Self::i() * self + (Self::one() - self * self).sqrt()).ln()
+    }
+    // acos(z) = -i*ln(z + i*sqrt(1 - z^2))
+    pub fn acos(self) -> Self {
+        -Self::i() * (self + Self::i() * (Self::one() - self * self).sqrt()).ln()
+    }
+    // atan(z) = i/2 * (ln(1 - iz) - ln(1 + iz))
+    pub fn atan(self) -> Self {
+        let (one, i) = (Self::one(), Self::i());
+        i / T::of(2.) * ((one - i * self).ln() - (one + i * self).ln())
+    }
+
+    // hyperbolic functions
+    pub fn sinh(self) -> Self {
+        Self::new(
+            self.re.sinh() * self.im.cos(),
+            self.re.cosh() * self.im.sin(),
+        )
+    }
+    pub fn cosh(self) -> Self {
+        Self::new(
+            self.re.cosh() * self.im.cos(),
+            self.re.sinh() * self.im.sin(),
+        )
+    }
+    // the same as tan with the parts swapped, tanh(z) = -i*tan(iz)
+    pub fn tanh(self) -> Self {
+        let two = T::of(2.);
+        let (a, b) = (two * self.re, two * self.im);
+        Self::new(
+            a.tanh() / (T::one() + b.cos() / a.cosh()),
+            b.sin() / (a.cosh() + b.cos()),
+        )
+    }
+    // asinh(z) = ln(z + sqrt(z^2 + 1))
+    pub fn asinh(self) -> Self {
+        (self + (self * self + T::one()).sqrt()).ln()
+    }
+    // acosh(z) = ln(z + sqrt(z + 1)*sqrt(z - 1))
+    pub fn acosh(self) -> Self {
+        (self + (self + T::one()).sqrt() * (self - T::one()).sqrt()).ln()
+    }
+    // atanh(z) = (ln(1 + z) - ln(1 - z)) / 2
+    pub fn atanh(self) -> Self {
+        let one = Self::one();
+        ((one + self).ln() - (one - self).ln()) / T::of(2.)
     }
 }
 
+// the imaginary unit of Complex<f64>, Complex::i() gives the one of any precision
 pub fn i() -> Complex {
-    Complex { re: 0., im: 1. }
+    Complex::i()
 }
 
-impl Display for Complex {
+// Prints a+bi or a-bi, which parses back into the same number. The sign of a negative zero
+// imaginary part is kept, and a precision like {:.3} applies to both parts.
+impl<T: Real> Display for Complex<T> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        if self.im < 0. {
-            write!(f, "{}{}i", self.re, self.im)
+        Display::fmt(&self.re, f)?;
+        if self.im.is_sign_negative() && !self.im.is_nan() {
+            write!(f, "-")?;
         } else {
-            write!(f, "{}+{}i", self.re, self.im)
+            write!(f, "+")?;
         }
+        Display::fmt(&self.im.abs(), f)?;
+        write!(f, "i")
+    }
+}
+
+// what went wrong when parsing a complex number
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseComplexError {
+    source: String,
+}
+impl Display for ParseComplexError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Invalid complex number {:?}, expected a+bi, a-bi, a or bi",
+            self.source
+        )
+    }
+}
+impl Error for ParseComplexError {}
+
+// Reads a, bi, a+bi and a-bi, where i alone stands for 1i and spaces are ignored.
+// Either part can be anything parse_real understands.
+fn parse<T: Real>(
+    source: &str,
+    parse_real: impl Fn(&str) -> Option<T>,
+    exponents: bool,
+) -> Result<Complex<T>, ParseComplexError> {
+    let error = || ParseComplexError {
+        source: source.to_string(),
+    };
+    let s = source
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .collect::<String>();
+    let Some(imaginary) = s.strip_suffix('i') else {
+        return parse_real(&s).map(Complex::from_real).ok_or_else(error);
+    };
+
+    // the imaginary part starts at the last sign that isn't the sign of an exponent
+    let bytes = imaginary.as_bytes();
+    let split = (1..bytes.len())
+        .rev()
+        .find(|&j| {
+            let exponent = exponents
+                && j >= 2
+                && matches!(bytes[j - 1], b'e' | b'E')
+                && (bytes[j - 2].is_ascii_digit() || bytes[j - 2] == b'.');
+            matches!(bytes[j], b'+' | b'-') && !exponent
+        })
+        .unwrap_or(0);
+    let (re, im) = imaginary.split_at(split);
+    let re = if re.is_empty() {
+        T::zero()
+    } else {
+        parse_real(re).ok_or_else(error)?
+    };
+    let im = match im {
+        "" | "+" => T::one(),
+        "-" => -T::one(),
+        _ => parse_real(im).ok_or_else(error)?,
+    };
+    Ok(Complex::new(re, im))
+}
+
+impl<T: Real> FromStr for Complex<T> {
+    type Err = ParseComplexError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse(s, |s| s.parse::<T>().ok(), true)
     }
 }
 
 // basic arithmetic
-impl Add<Self> for Complex {
+impl<T: Real> Neg for Complex<T> {
+    type Output = Self;
+    fn neg(self) -> Self::Output {
+        Self::Output {
+            re: -self.re,
+            im: -self.im,
+        }
+    }
+}
+impl<T: Real> Add<Self> for Complex<T> {
     type Output = Self;
     fn add(self, rhs: Self) -> Self::Output {
         Self::Output {
@@ -71,7 +368,7 @@ impl Add<Self> for Complex {
         }
     }
 }
-impl Sub<Self> for Complex {
+impl<T: Real> Sub<Self> for Complex<T> {
     type Output = Self;
     fn sub(self, rhs: Self) -> Self::Output {
         Self::Output {
@@ -80,7 +377,7 @@ impl Sub<Self> for Complex {
         }
     }
 }
-impl Mul<Self> for Complex {
+impl<T: Real> Mul<Self> for Complex<T> {
     type Output = Self;
     fn mul(self, rhs: Self) -> Self::Output {
         Self::Output {
@@ -89,7 +386,7 @@ impl Mul<Self> for Complex {
         }
     }
 }
-impl Div<Self> for Complex {
+impl<T: Real> Div<Self> for Complex<T> {
     type Output = Self;
     fn div(self, rhs: Self) -> Self::Output {
         // (a+bi)/(c+di) = (a+bi)(c-di)/(c^2+d^2)
@@ -102,161 +399,234 @@ impl Div<Self> for Complex {
         }
     }
 }
+// The remainder after dividing by rhs and truncating both parts of the quotient,
+// like the remainder of gaussian integers
+impl<T: Real> Rem<Self> for Complex<T> {
+    type Output = Self;
+    fn rem(self, rhs: Self) -> Self::Output {
+        let quotient = self / rhs;
+        self - rhs * Self::new(quotient.re.trunc(), quotient.im.trunc())
+    }
+}
 
-// for f64, assume real
-impl Add<f64> for Complex {
+// for a real number, assume real
+impl<T: Real> Add<T> for Complex<T> {
     type Output = Self;
-    fn add(self, rhs: f64) -> Self::Output {
+    fn add(self, rhs: T) -> Self::Output {
         Self::Output {
             re: self.re + rhs,
             im: self.im,
         }
     }
 }
-impl Sub<f64> for Complex {
+impl<T: Real> Sub<T> for Complex<T> {
     type Output = Self;
-    fn sub(self, rhs: f64) -> Self::Output {
+    fn sub(self, rhs: T) -> Self::Output {
         Self::Output {
             re: self.re - rhs,
             im: self.im,
         }
     }
 }
-impl Mul<f64> for Complex {
+impl<T: Real> Mul<T> for Complex<T> {
     type Output = Self;
-    fn mul(self, rhs: f64) -> Self::Output {
+    fn mul(self, rhs: T) -> Self::Output {
         Self::Output {
             re: self.re * rhs,
             im: self.im * rhs,
         }
     }
 }
-impl Div<f64> for Complex {
+impl<T: Real> Div<T> for Complex<T> {
     type Output = Self;
-    fn div(self, rhs: f64) -> Self::Output {
+    fn div(self, rhs: T) -> Self::Output {
         Self::Output {
             re: self.re / rhs,
             im: self.im / rhs,
         }
     }
 }
-
-impl Add<Complex> for f64 {
-    type Output = Complex;
-    fn add(self, rhs: Complex) -> Self::Output {
-        Self::Output {
-            re: rhs.re + self,
-            im: rhs.im,
-        }
+impl<T: Real> Rem<T> for Complex<T> {
+    type Output = Self;
+    fn rem(self, rhs: T) -> Self::Output {
+        self % Self::from_real(rhs)
     }
 }
-impl Sub<Complex> for f64 {
-    type Output = Complex;
-    fn sub(self, rhs: Complex) -> Self::Output {
-        Self::Output {
-            re: self - rhs.re,
-            im: -rhs.im,
+
+// the real number on the left, which has to be written out for every type
+macro_rules! impl_real_lhs {
+    ($($t:ty),*) => {$(
+        impl Add<Complex<$t>> for $t {
+            type Output = Complex<$t>;
+            fn add(self, rhs: Complex<$t>) -> Self::Output {
+                rhs + self
+            }
         }
-    }
-}
-impl Mul<Complex> for f64 {
-    type Output = Complex;
-    fn mul(self, rhs: Complex) -> Self::Output {
-        rhs * self
-    }
-}
-impl Div<Complex> for f64 {
-    type Output = Complex;
-    fn div(self, rhs: Complex) -> Self::Output {
-        Complex { re: self, im: 0. } / rhs
-    }
+        impl Sub<Complex<$t>> for $t {
+            type Output = Complex<$t>;
+            fn sub(self, rhs: Complex<$t>) -> Self::Output {
+                Complex::from_real(self) - rhs
+            }
+        }
+        impl Mul<Complex<$t>> for $t {
+            type Output = Complex<$t>;
+            fn mul(self, rhs: Complex<$t>) -> Self::Output {
+                rhs * self
+            }
+        }
+        impl Div<Complex<$t>> for $t {
+            type Output = Complex<$t>;
+            fn div(self, rhs: Complex<$t>) -> Self::Output {
+                Complex::from_real(self) / rhs
+            }
+        }
+    )*};
 }
+impl_real_lhs!(f32, f64);
 
 // assign
-impl AddAssign for Complex {
+impl<T: Real> AddAssign for Complex<T> {
     fn add_assign(&mut self, rhs: Self) {
         let res = *self + rhs;
         *self = res;
     }
 }
-impl AddAssign<f64> for Complex {
-    fn add_assign(&mut self, rhs: f64) {
+impl<T: Real> AddAssign<T> for Complex<T> {
+    fn add_assign(&mut self, rhs: T) {
         let res = *self + rhs;
         *self = res;
     }
 }
-impl SubAssign for Complex {
+impl<T: Real> SubAssign for Complex<T> {
     fn sub_assign(&mut self, rhs: Self) {
         let res = *self - rhs;
         *self = res
     }
 }
-impl SubAssign<f64> for Complex {
-    fn sub_assign(&mut self, rhs: f64) {
+impl<T: Real> SubAssign<T> for Complex<T> {
+    fn sub_assign(&mut self, rhs: T) {
         let res = *self - rhs;
         *self = res
     }
 }
-impl MulAssign for Complex {
+impl<T: Real> MulAssign for Complex<T> {
     fn mul_assign(&mut self, rhs: Self) {
         let res = *self * rhs;
         *self = res;
     }
 }
-impl MulAssign<f64> for Complex {
-    fn mul_assign(&mut self, rhs: f64) {
+impl<T: Real> MulAssign<T> for Complex<T> {
+    fn mul_assign(&mut self, rhs: T) {
         let res = *self * rhs;
         *self = res;
     }
 }
-impl DivAssign for Complex {
+impl<T: Real> DivAssign for Complex<T> {
     fn div_assign(&mut self, rhs: Self) {
         let res = *self / rhs;
         *self = res;
     }
 }
-impl DivAssign<f64> for Complex {
-    fn div_assign(&mut self, rhs: f64) {
+impl<T: Real> DivAssign<T> for Complex<T> {
+    fn div_assign(&mut self, rhs: T) {
         let res = *self / rhs;
         *self = res;
     }
 }
 
 // Shorthand
-impl From<f64> for Complex {
-    fn from(value: f64) -> Self {
+impl<T: Real> From<T> for Complex<T> {
+    fn from(value: T) -> Self {
         // assume value is real
-        Self::new(value, 0.)
+        Self::from_real(value)
+    }
+}
+// without any loss, the other way around needs cast
+impl From<Complex<f32>> for Complex<f64> {
+    fn from(value: Complex<f32>) -> Self {
+        value.cast()
     }
 }
 
 // num_traits implementations
-impl Zero for Complex {
+impl<T: Real> Zero for Complex<T> {
     fn zero() -> Self {
-        Self { re: 0., im: 0. }
+        Self {
+            re: T::zero(),
+            im: T::zero(),
+        }
     }
     fn is_zero(&self) -> bool {
-        *self == Self { re: 0., im: 0. }
+        *self == Self::zero()
     }
 }
 
 // assume real
-impl One for Complex {
+impl<T: Real> One for Complex<T> {
     fn one() -> Self {
-        Self { re: 1., im: 0. }
+        Self {
+            re: T::one(),
+            im: T::zero(),
+        }
     }
     fn is_one(&self) -> bool
     where
         Self: PartialEq,
     {
-        *self == Self { re: 1., im: 0. }
+        *self == Self::one()
+    }
+}
+
+impl<T: Real> Num for Complex<T> {
+    type FromStrRadixErr = ParseComplexError;
+    // exponents are only read in base 10, since e is a digit in larger bases
+    fn from_str_radix(s: &str, radix: u32) -> Result<Self, Self::FromStrRadixErr> {
+        parse(s, |s| T::from_str_radix(s, radix).ok(), radix == 10)
+    }
+}
+
+// sums and products of iterators
+impl<T: Real> Sum for Complex<T> {
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.fold(Self::zero(), |a, b| a + b)
+    }
+}
+impl<'a, T: Real> Sum<&'a Complex<T>> for Complex<T> {
+    fn sum<I: Iterator<Item = &'a Complex<T>>>(iter: I) -> Self {
+        iter.copied().sum()
+    }
+}
+impl<T: Real> Product for Complex<T> {
+    fn product<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.fold(Self::one(), |a, b| a * b)
+    }
+}
+impl<'a, T: Real> Product<&'a Complex<T>> for Complex<T> {
+    fn product<I: Iterator<Item = &'a Complex<T>>>(iter: I) -> Self {
+        iter.copied().product()
     }
 }
 
 // Matrix implementations
-impl Mul<Matrix<Complex, Dyn, Dyn, VecStorage<Complex, Dyn, Dyn>>> for Complex {
-    type Output = Matrix<Complex, Dyn, Dyn, VecStorage<Complex, Dyn, Dyn>>;
-    fn mul(self, rhs: Matrix<Complex, Dyn, Dyn, VecStorage<Complex, Dyn, Dyn>>) -> Self::Output {
+type DMatrixOf<T> = Matrix<Complex<T>, Dyn, Dyn, VecStorage<Complex<T>, Dyn, Dyn>>;
+type DVectorOf<T> = Matrix<Complex<T>, Dyn, Const<1>, VecStorage<Complex<T>, Dyn, Const<1>>>;
+
+impl<T: Real> Mul<DMatrixOf<T>> for Complex<T> {
+    type Output = DMatrixOf<T>;
+    fn mul(self, rhs: DMatrixOf<T>) -> Self::Output {
+        let mut res = rhs.to_owned();
+
+        for rhs in res.as_mut_slice().iter_mut() {
+            *rhs *= self
+        }
+
+        res
+    }
+}
+
+impl<T: Real> Mul<&DMatrixOf<T>> for Complex<T> {
+    type Output = DMatrixOf<T>;
+    fn mul(self, rhs: &DMatrixOf<T>) -> Self::Output {
         let mut res = rhs.to_owned();
 
         for rhs in res.as_mut_slice().iter_mut() {
@@ -267,12 +637,9 @@ impl Mul<Matrix<Complex, Dyn, Dyn, VecStorage<Complex, Dyn, Dyn>>> for Complex {
     }
 }
 
-impl Mul<&Matrix<Complex, Dyn, Const<1>, VecStorage<Complex, Dyn, Const<1>>>> for Complex {
-    type Output = Matrix<Complex, Dyn, Const<1>, VecStorage<Complex, Dyn, Const<1>>>;
-    fn mul(
-        self,
-        rhs: &Matrix<Complex, Dyn, Const<1>, VecStorage<Complex, Dyn, Const<1>>>,
-    ) -> Self::Output {
+impl<T: Real> Mul<&DVectorOf<T>> for Complex<T> {
+    type Output = DVectorOf<T>;
+    fn mul(self, rhs: &DVectorOf<T>) -> Self::Output {
         let mut res = rhs.to_owned();
 
         for rhs in res.as_mut_slice().iter_mut() {
@@ -283,12 +650,9 @@ impl Mul<&Matrix<Complex, Dyn, Const<1>, VecStorage<Complex, Dyn, Const<1>>>> fo
     }
 }
 
-impl Mul<Matrix<Complex, Dyn, Const<1>, VecStorage<Complex, Dyn, Const<1>>>> for Complex {
-    type Output = Matrix<Complex, Dyn, Const<1>, VecStorage<Complex, Dyn, Const<1>>>;
-    fn mul(
-        self,
-        rhs: Matrix<Complex, Dyn, Const<1>, VecStorage<Complex, Dyn, Const<1>>>,
-    ) -> Self::Output {
+impl<T: Real> Mul<DVectorOf<T>> for Complex<T> {
+    type Output = DVectorOf<T>;
+    fn mul(self, rhs: DVectorOf<T>) -> Self::Output {
         let mut res = rhs.to_owned();
 
         for rhs in res.as_mut_slice().iter_mut() {
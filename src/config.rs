@@ -0,0 +1,339 @@
+use std::{
+    env::Args,
+    io::{Error, ErrorKind},
+    path::{Path, PathBuf},
+};
+
+use crate::{checkpoint::Checkpoint, export::Exporter, expression::Expression, scenario::Scenario};
+
+const USAGE: &str = "Usage: cargo run --release -- ['number of dimensions'] [Optional 'visible'] [Optional '--scenario' FILE] [Optional '--potential' EXPRESSION] [Optional '--export' FILE] [Optional '--export-every' N] [Optional '--checkpoint' FILE] [Optional '--resume' FILE] [Optional '--steps' N | '--time' T] [Optional '--interval' N] [Optional '--precision' f64/f32] [Optional '--imaginary-time' N | '--eigenstates' N | '--scattering' packet/spectrum]. \
+    Without the libraries bevy needs, use cargo run --release --no-default-features -- 1 false";
+// number of steps and print interval used in headless mode when nothing else is specified
+const DEFAULT_STEPS: usize = 1000;
+const DEFAULT_INTERVAL: usize = 100;
+
+// The options given on the command line
+#[derive(Debug)]
+pub struct Config {
+    dims: u8,
+    vis: bool,
+    scenario: Scenario,
+    steps: Option<usize>,
+    time: Option<f64>,
+    interval: usize,
+    // number of stationary states to find with imaginary time propagation (1D, headless only)
+    imaginary_time: Option<usize>,
+    // number of eigenstates to find with the direct solver (1D, headless only)
+    eigenstates: Option<usize>,
+    // how to measure the transmission through the potential (1D, headless only)
+    scattering: Option<ScatteringMode>,
+    // what the wave function is stored and stepped in (headless only)
+    precision: Precision,
+    // where to write frames of the wave function to
+    exporter: Option<Exporter>,
+    // where to save the state of the run to, every interval steps in headless mode
+    checkpoint: Option<PathBuf>,
+    // the state to pick up from instead of the initial wave function
+    resume: Option<Checkpoint>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Precision {
+    #[default]
+    Double,
+    // f32, which is faster and takes half the memory, at about 1e-6 relative accuracy
+    Single,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ScatteringMode {
+    // runs the initial packet into the potential and integrates both sides afterwards
+    Packet,
+    // T(E) from the stationary scattering problem
+    Spectrum,
+}
+impl Config {
+    pub fn construct(args: Args) -> Result<Self, Error> {
+        // shadow iterator without first argument (not needed)
+        let mut args = args.skip(1).peekable();
+        // make sure that there is at least a number of dimensions
+        if args.peek().is_none() {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("Missing number of dimensions. {USAGE}"),
+            ));
+        }
+
+        // note: unwrapping is safe because we've already checked that there is an argument
+        let dims = args.next().unwrap();
+        let dims = dims.parse::<u8>().map_err(|_| {
+            Error::new(
+                ErrorKind::InvalidInput,
+                format!("Failed to parse number of dimensions, got {dims}"),
+            )
+        })?;
+        if !(1..=2).contains(&dims) {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("Invalid number of dimensions. Expected one or two, got {dims}"),
+            ));
+        }
+
+        let vis = if let Some(vis_arg) = args.next_if(|arg| !arg.starts_with("--")) {
+            if vis_arg != "1" && vis_arg != "true" {
+                // if there exists a vis_arg and it is neither 1 nor true, assume false
+                false
+            } else {
+                // both 1 and true are valid arguments for true
+                true
+            }
+        } else {
+            // defaiult to true if no value was specified,
+            // unless this was built without the visual mode
+            cfg!(feature = "visual")
+        };
+        if vis && !cfg!(feature = "visual") {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "This build has no visual mode, rebuild with --features visual \
+                 or pass false to run headless",
+            ));
+        }
+
+        // the remaining arguments are options, given as "--name value"
+        let mut scenario = None;
+        let mut potential = None;
+        let mut steps = None;
+        let mut time = None;
+        let mut interval = DEFAULT_INTERVAL;
+        let mut precision = Precision::default();
+        let mut imaginary_time = None;
+        let mut eigenstates = None;
+        let mut scattering = None;
+        let mut export = None;
+        let mut export_every = None;
+        let mut checkpoint = None;
+        let mut resume = None;
+        while let Some(option) = args.next() {
+            let Some(value) = args.next() else {
+                return Err(Error::new(
+                    ErrorKind::InvalidInput,
+                    format!("Missing value for option {option}. {USAGE}"),
+                ));
+            };
+            let invalid = || {
+                Error::new(
+                    ErrorKind::InvalidInput,
+                    format!("Invalid value for option {option}, got {value}"),
+                )
+            };
+            match option.as_str() {
+                "--scenario" => scenario = Some(Scenario::load(&value, dims)?),
+                "--potential" => {
+                    potential = Some(Expression::parse(&value).map_err(|e| {
+                        Error::new(ErrorKind::InvalidInput, format!("Invalid potential: {e}"))
+                    })?)
+                }
+                "--export" => export = Some(value),
+                "--checkpoint" => checkpoint = Some(PathBuf::from(value)),
+                "--resume" => resume = Some(Checkpoint::load(&value)?),
+                "--export-every" => export_every = Some(positive(&value).ok_or_else(invalid)?),
+                "--steps" => steps = Some(value.parse::<usize>().map_err(|_| invalid())?),
+                "--time" => time = Some(value.parse::<f64>().map_err(|_| invalid())?),
+                "--interval" => interval = positive(&value).ok_or_else(invalid)?,
+                "--precision" => {
+                    precision = match value.as_str() {
+                        "f64" => Precision::Double,
+                        "f32" => Precision::Single,
+                        _ => return Err(invalid()),
+                    }
+                }
+                "--imaginary-time" => {
+                    imaginary_time = Some(value.parse::<usize>().map_err(|_| invalid())?)
+                }
+                "--eigenstates" => {
+                    eigenstates = Some(value.parse::<usize>().map_err(|_| invalid())?)
+                }
+                "--scattering" => {
+                    scattering = Some(match value.as_str() {
+                        "packet" => ScatteringMode::Packet,
+                        "spectrum" => ScatteringMode::Spectrum,
+                        _ => return Err(invalid()),
+                    })
+                }
+                _ => {
+                    return Err(Error::new(
+                        ErrorKind::InvalidInput,
+                        format!("Unknown option {option}. {USAGE}"),
+                    ))
+                }
+            }
+        }
+        if steps.is_some() && time.is_some() {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "Only one of --steps and --time can be specified",
+            ));
+        }
+
+        // the stationary states and the scattering analysis only exist in the 1D headless mode
+        if dims != 1 || vis {
+            let given = [
+                ("--imaginary-time", imaginary_time.is_some()),
+                ("--eigenstates", eigenstates.is_some()),
+                ("--scattering", scattering.is_some()),
+            ];
+            if let Some((option, _)) = given.iter().find(|(_, given)| *given) {
+                return Err(Error::new(
+                    ErrorKind::InvalidInput,
+                    format!("{option} only works in one dimension without visualization"),
+                ));
+            }
+        }
+        // as do the visual mode and the analysis modes, which all work in f64
+        let analysis = imaginary_time.is_some() || eigenstates.is_some() || scattering.is_some();
+        if precision == Precision::Single && (vis || analysis) {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "--precision f32 only works for headless time evolution",
+            ));
+        }
+
+        if export.is_none() && export_every.is_some() {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "--export-every needs a file to --export to",
+            ));
+        }
+        let exporter = export
+            .map(|path| Exporter::new(path, export_every))
+            .transpose()?;
+
+        // a checkpoint brings its own scenario
+        if let Some(resume) = &resume {
+            if scenario.is_some() || potential.is_some() {
+                return Err(Error::new(
+                    ErrorKind::InvalidInput,
+                    "--resume can't be combined with --scenario or --potential, \
+                     the checkpoint already contains the scenario",
+                ));
+            }
+            if resume.dims != dims {
+                return Err(Error::new(
+                    ErrorKind::InvalidInput,
+                    format!(
+                        "The checkpoint is from a run in {} dimensions, not {dims}",
+                        resume.dims
+                    ),
+                ));
+            }
+            scenario = Some(resume.scenario.clone());
+        }
+
+        // the potential replaces the one from the scenario, no matter the order of the options
+        let mut scenario = scenario.unwrap_or_else(|| Scenario::default_for(dims));
+        if let Some(potential) = potential {
+            scenario.set_potential(potential);
+        }
+
+        Ok(Self {
+            dims,
+            vis,
+            scenario,
+            steps,
+            time,
+            interval,
+            imaginary_time,
+            eigenstates,
+            scattering,
+            precision,
+            exporter,
+            checkpoint,
+            resume,
+        })
+    }
+    pub fn dims(&self) -> u8 {
+        self.dims
+    }
+    pub fn vis(&self) -> bool {
+        self.vis
+    }
+    pub fn scenario(&self) -> &Scenario {
+        &self.scenario
+    }
+    pub fn interval(&self) -> usize {
+        self.interval
+    }
+    pub fn imaginary_time(&self) -> Option<usize> {
+        self.imaginary_time
+    }
+    pub fn eigenstates(&self) -> Option<usize> {
+        self.eigenstates
+    }
+    pub fn scattering(&self) -> Option<ScatteringMode> {
+        self.scattering
+    }
+    pub fn precision(&self) -> Precision {
+        self.precision
+    }
+    pub fn exporter(&self) -> Option<&Exporter> {
+        self.exporter.as_ref()
+    }
+    pub fn checkpoint(&self) -> Option<&Path> {
+        self.checkpoint.as_deref()
+    }
+    pub fn resume(&self) -> Option<&Checkpoint> {
+        self.resume.as_ref()
+    }
+    // checkpoints saved from the visual mode go to checkpoint.qpc unless --checkpoint is given
+    #[cfg(feature = "visual")]
+    pub fn visual_checkpoint(&self) -> PathBuf {
+        self.checkpoint
+            .clone()
+            .unwrap_or_else(|| PathBuf::from("checkpoint.qpc"))
+    }
+    // frames saved from the visual mode go to frame_<step>.csv unless --export is given
+    #[cfg(feature = "visual")]
+    pub fn visual_exporter(&self) -> Exporter {
+        self.exporter
+            .clone()
+            .unwrap_or_else(|| Exporter::new("frame.csv", None).unwrap())
+    }
+    // number of time steps of size dt to run in headless mode
+    pub fn steps(&self, dt: f64) -> usize {
+        self.given_steps(dt).unwrap_or(DEFAULT_STEPS)
+    }
+    // the number of steps from --steps or --time, if either was given
+    pub fn given_steps(&self, dt: f64) -> Option<usize> {
+        match (self.steps, self.time) {
+            (Some(steps), _) => Some(steps),
+            (None, Some(time)) => Some((time / dt).round() as usize),
+            (None, None) => None,
+        }
+    }
+}
+
+// a number of steps, which has to be at least one
+fn positive(value: &str) -> Option<usize> {
+    value.parse::<usize>().ok().filter(|steps| *steps > 0)
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            dims: 1,
+            vis: cfg!(feature = "visual"),
+            scenario: Scenario::default(),
+            steps: None,
+            time: None,
+            interval: DEFAULT_INTERVAL,
+            imaginary_time: None,
+            eigenstates: None,
+            scattering: None,
+            precision: Precision::default(),
+            exporter: None,
+            checkpoint: None,
+            resume: None,
+        }
+    }
+}
@@ -1,6 +0,0 @@
-use std::f64::consts::PI;
-// constants for simulation
-pub const H: f64 = 1.;
-pub const H_BAR: f64 = H / (2. * PI);
-pub const M: f64 = 1.;
-pub const L: f64 = 8.; //8
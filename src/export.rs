@@ -0,0 +1,239 @@
+use std::{
+    fs,
+    io::{Error, ErrorKind},
+    path::{Path, PathBuf},
+};
+
+use nalgebra::{DMatrix, DVector};
+use serde_json::{json, Value};
+
+use crate::complex::Complex;
+
+// Writes frames of the simulation to files for post-processing. The format follows from the
+// extension of the path:
+//
+// .csv  - one row per grid point with the columns x (and z), re, im and prob
+// .npy  - the same values as an array of shape (n, 4) in 1D or (n, n, 5) in 2D,
+//         i.e. the last axis holds x, (z,) re, im and prob
+// .json - the step, the time and the grid as lists, with re, im and prob as matrices in 2D
+//
+// In 2D the rows of the csv go through z first, so np.loadtxt(..).reshape(n, n, 5) gives the
+// same layout as the npy file. With every set, a frame is written every that many steps to
+// a file of its own, with the step number added to the name, e.g. psi_000100.csv.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Exporter {
+    path: PathBuf,
+    format: Format,
+    every: Option<usize>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Format {
+    Csv,
+    Npy,
+    Json,
+}
+
+// one frame of the wave function, with psi stored row by row, i.e. along the last axis first
+pub struct Frame {
+    step: usize,
+    time: f64,
+    axes: Vec<(&'static str, Vec<f64>)>,
+    psi: Vec<Complex>,
+}
+
+impl Exporter {
+    pub fn new(path: impl Into<PathBuf>, every: Option<usize>) -> Result<Self, Error> {
+        let path = path.into();
+        let format = match path.extension().and_then(|e| e.to_str()) {
+            Some("csv") => Format::Csv,
+            Some("npy") => Format::Npy,
+            Some("json") => Format::Json,
+            _ => {
+                return Err(Error::new(
+                    ErrorKind::InvalidInput,
+                    format!(
+                        "Unknown export format for {}, expected .csv, .npy or .json",
+                        path.display()
+                    ),
+                ))
+            }
+        };
+        if every == Some(0) {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "Frames have to be exported at least every step, not every 0",
+            ));
+        }
+        Ok(Self {
+            path,
+            format,
+            every,
+        })
+    }
+
+    // whether the frame after step should be written, a single frame is written at the end
+    pub fn wants(&self, step: usize, last: usize) -> bool {
+        match self.every {
+            Some(every) => step % every == 0 || step == last,
+            None => step == last,
+        }
+    }
+
+    // writes the frame to the path, or to a file of its own if every is set,
+    // and returns the file that was written
+    pub fn write(&self, frame: &Frame) -> Result<PathBuf, Error> {
+        let path = if self.every.is_some() {
+            self.numbered(frame.step)
+        } else {
+            self.path.clone()
+        };
+        write(&path, self.format, frame)?;
+        Ok(path)
+    }
+
+    // always writes to a file of its own, for saving single frames one after another
+    pub fn write_numbered(&self, frame: &Frame) -> Result<PathBuf, Error> {
+        let path = self.numbered(frame.step);
+        write(&path, self.format, frame)?;
+        Ok(path)
+    }
+
+    // the path with the step added to the file name
+    fn numbered(&self, step: usize) -> PathBuf {
+        let stem = self
+            .path
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .unwrap_or("frame");
+        let extension = self.path.extension().and_then(|e| e.to_str()).unwrap_or("");
+        self.path
+            .with_file_name(format!("{stem}_{step:06}.{extension}"))
+    }
+}
+
+impl Frame {
+    pub fn from_1d(step: usize, time: f64, x: &[f64], psi: &DVector<Complex>) -> Self {
+        Self {
+            step,
+            time,
+            axes: vec![("x", x.to_vec())],
+            psi: psi.iter().copied().collect(),
+        }
+    }
+
+    // rows of psi are along x and columns along z
+    pub fn from_2d(step: usize, time: f64, x: &[f64], z: &[f64], psi: &DMatrix<Complex>) -> Self {
+        Self {
+            step,
+            time,
+            axes: vec![("x", x.to_vec()), ("z", z.to_vec())],
+            psi: psi
+                .row_iter()
+                .flat_map(|row| row.iter().copied().collect::<Vec<Complex>>())
+                .collect(),
+        }
+    }
+
+    fn shape(&self) -> Vec<usize> {
+        self.axes.iter().map(|(_, values)| values.len()).collect()
+    }
+
+    // the grid coordinates of the point at index, followed by re, im and |psi|^2
+    fn row(&self, index: usize) -> Vec<f64> {
+        let mut row = Vec::with_capacity(self.axes.len() + 3);
+        // the last axis changes the fastest
+        let mut stride = self.psi.len();
+        for (_, values) in &self.axes {
+            stride /= values.len();
+            row.push(values[index / stride % values.len()]);
+        }
+        let p = self.psi[index];
+        row.extend([p.real(), p.imag(), p.abs_squared()]);
+        row
+    }
+
+    fn columns(&self) -> Vec<&'static str> {
+        let mut columns = self.axes.iter().map(|(name, _)| *name).collect::<Vec<_>>();
+        columns.extend(["re", "im", "prob"]);
+        columns
+    }
+}
+
+fn write(path: &Path, format: Format, frame: &Frame) -> Result<(), Error> {
+    let bytes = match format {
+        Format::Csv => csv(frame).into_bytes(),
+        Format::Npy => npy(frame),
+        Format::Json => json(frame).to_string().into_bytes(),
+    };
+    fs::write(path, bytes).map_err(|e| {
+        Error::new(
+            e.kind(),
+            format!("Failed to write frame to {}: {e}", path.display()),
+        )
+    })
+}
+
+fn csv(frame: &Frame) -> String {
+    let mut res = frame.columns().join(",");
+    res.push('\n');
+    for index in 0..frame.psi.len() {
+        let row = frame.row(index);
+        res.push_str(
+            &row.iter()
+                .map(|v| v.to_string())
+                .collect::<Vec<String>>()
+                .join(","),
+        );
+        res.push('\n');
+    }
+    res
+}
+
+// Version 1.0 of the format: a magic string, the length of the header, and a python dict
+// describing the array, padded so that the data starts at a multiple of 64 bytes.
+// The data follows as little endian f64 in C order.
+fn npy(frame: &Frame) -> Vec<u8> {
+    let mut shape = frame.shape();
+    shape.push(frame.columns().len());
+    let shape = shape.iter().map(|n| format!("{n}, ")).collect::<String>();
+    let mut header = format!("{{'descr': '<f8', 'fortran_order': False, 'shape': ({shape}), }}");
+    // magic (6) + version (2) + header length (2) + header + newline
+    let unpadded = 10 + header.len() + 1;
+    header.push_str(&" ".repeat(unpadded.next_multiple_of(64) - unpadded));
+    header.push('\n');
+
+    let mut res = b"\x93NUMPY\x01\x00".to_vec();
+    res.extend((header.len() as u16).to_le_bytes());
+    res.extend(header.as_bytes());
+    for index in 0..frame.psi.len() {
+        for value in frame.row(index) {
+            res.extend(value.to_le_bytes());
+        }
+    }
+    res
+}
+
+fn json(frame: &Frame) -> Value {
+    let mut res = json!({
+        "step": frame.step,
+        "time": frame.time,
+    });
+    for (name, values) in &frame.axes {
+        res[*name] = json!(values);
+    }
+    // a list in 1D and a list of rows in 2D
+    let row_length = frame.shape().last().copied().unwrap_or(1);
+    let values = |f: fn(&Complex) -> f64| {
+        let values = frame.psi.iter().map(f).collect::<Vec<f64>>();
+        if frame.axes.len() == 1 {
+            json!(values)
+        } else {
+            json!(values.chunks(row_length).collect::<Vec<&[f64]>>())
+        }
+    };
+    res["re"] = values(Complex::real);
+    res["im"] = values(Complex::imag);
+    res["prob"] = values(Complex::abs_squared);
+    res
+}
@@ -0,0 +1,566 @@
+use std::{
+    f64::consts::{E, PI},
+    fmt,
+};
+
+use serde::{Deserialize, Serialize};
+
+// A formula in x, y and t that is parsed once and can then be evaluated at every grid point,
+// which allows for potentials that are only known at runtime, e.g.
+//
+// let v = Expression::parse("0.5*x^2 + 3*exp(-(x-1)^2/0.1)")?;
+// let barrier = Expression::parse("step(x-2.5)*step(3-x)")?;
+//
+// Besides + - * / and ^ (which binds the strongest and groups to the right, so -x^2 is -(x^2)
+// and 2^3^2 is 2^9), the comparisons < <= > >= are one if they hold and zero otherwise.
+// The constants pi and e can be used as well as the functions listed in Function::from_name.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Expression {
+    source: String,
+    root: Node,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Variable {
+    X,
+    Y,
+    T,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Node {
+    Number(f64),
+    Variable(Variable),
+    Negate(Box<Node>),
+    Binary(Operator, Box<Node>, Box<Node>),
+    Call(Function, Vec<Node>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Operator {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Power,
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Function {
+    Sin,
+    Cos,
+    Tan,
+    Asin,
+    Acos,
+    Atan,
+    Sinh,
+    Cosh,
+    Tanh,
+    Exp,
+    Ln,
+    Log10,
+    Sqrt,
+    Abs,
+    Sign,
+    Floor,
+    Ceil,
+    // one for positive arguments, zero otherwise
+    Step,
+    // one for |x| < 1/2, zero otherwise
+    Rect,
+    Min,
+    Max,
+    // if(condition, a, b) is a when condition is positive and b otherwise
+    If,
+}
+
+impl Function {
+    fn from_name(name: &str) -> Option<Self> {
+        Some(match name {
+            "sin" => Self::Sin,
+            "cos" => Self::Cos,
+            "tan" => Self::Tan,
+            "asin" => Self::Asin,
+            "acos" => Self::Acos,
+            "atan" => Self::Atan,
+            "sinh" => Self::Sinh,
+            "cosh" => Self::Cosh,
+            "tanh" => Self::Tanh,
+            "exp" => Self::Exp,
+            "ln" | "log" => Self::Ln,
+            "log10" => Self::Log10,
+            "sqrt" => Self::Sqrt,
+            "abs" => Self::Abs,
+            "sign" => Self::Sign,
+            "floor" => Self::Floor,
+            "ceil" => Self::Ceil,
+            "step" => Self::Step,
+            "rect" => Self::Rect,
+            "min" => Self::Min,
+            "max" => Self::Max,
+            "if" => Self::If,
+            _ => return None,
+        })
+    }
+
+    fn arity(self) -> usize {
+        match self {
+            Self::Min | Self::Max => 2,
+            Self::If => 3,
+            _ => 1,
+        }
+    }
+
+    fn apply(self, args: &[f64]) -> f64 {
+        let a = args[0];
+        match self {
+            Self::Sin => a.sin(),
+            Self::Cos => a.cos(),
+            Self::Tan => a.tan(),
+            Self::Asin => a.asin(),
+            Self::Acos => a.acos(),
+            Self::Atan => a.atan(),
+            Self::Sinh => a.sinh(),
+            Self::Cosh => a.cosh(),
+            Self::Tanh => a.tanh(),
+            Self::Exp => a.exp(),
+            Self::Ln => a.ln(),
+            Self::Log10 => a.log10(),
+            Self::Sqrt => a.sqrt(),
+            Self::Abs => a.abs(),
+            Self::Sign => {
+                if a == 0. {
+                    0.
+                } else {
+                    a.signum()
+                }
+            }
+            Self::Floor => a.floor(),
+            Self::Ceil => a.ceil(),
+            Self::Step => indicator(a > 0.),
+            Self::Rect => indicator(a.abs() < 0.5),
+            Self::Min => a.min(args[1]),
+            Self::Max => a.max(args[1]),
+            Self::If => {
+                if a > 0. {
+                    args[1]
+                } else {
+                    args[2]
+                }
+            }
+        }
+    }
+}
+
+fn indicator(condition: bool) -> f64 {
+    if condition {
+        1.
+    } else {
+        0.
+    }
+}
+
+impl Expression {
+    pub fn parse(source: &str) -> Result<Self, ParseError> {
+        Self::parse_with(source, true)
+    }
+
+    // for the static terms of a potential, which are sampled once and so can't depend on t
+    pub fn parse_static(source: &str) -> Result<Self, ParseError> {
+        Self::parse_with(source, false)
+    }
+
+    fn parse_with(source: &str, time: bool) -> Result<Self, ParseError> {
+        let tokens = tokenize(source)?;
+        let mut parser = Parser {
+            source,
+            tokens,
+            next: 0,
+            time,
+        };
+        let root = parser.comparison()?;
+        // everything has to be used up, otherwise something like "x y" would be accepted
+        let token = parser.peek();
+        if token.kind != Kind::End {
+            return Err(parser.error(token.position, "expected an operator"));
+        }
+        Ok(Self {
+            source: source.to_string(),
+            root,
+        })
+    }
+
+    pub fn eval(&self, x: f64, y: f64, t: f64) -> f64 {
+        eval(&self.root, x, y, t)
+    }
+
+    pub fn depends_on(&self, variable: Variable) -> bool {
+        depends_on(&self.root, variable)
+    }
+}
+
+fn eval(node: &Node, x: f64, y: f64, t: f64) -> f64 {
+    match node {
+        Node::Number(value) => *value,
+        Node::Variable(Variable::X) => x,
+        Node::Variable(Variable::Y) => y,
+        Node::Variable(Variable::T) => t,
+        Node::Negate(a) => -eval(a, x, y, t),
+        Node::Binary(operator, a, b) => {
+            let (a, b) = (eval(a, x, y, t), eval(b, x, y, t));
+            match operator {
+                Operator::Add => a + b,
+                Operator::Subtract => a - b,
+                Operator::Multiply => a * b,
+                Operator::Divide => a / b,
+                Operator::Power => a.powf(b),
+                Operator::Less => indicator(a < b),
+                Operator::LessEqual => indicator(a <= b),
+                Operator::Greater => indicator(a > b),
+                Operator::GreaterEqual => indicator(a >= b),
+            }
+        }
+        Node::Call(function, args) => function.apply(
+            &args
+                .iter()
+                .map(|arg| eval(arg, x, y, t))
+                .collect::<Vec<f64>>(),
+        ),
+    }
+}
+
+fn depends_on(node: &Node, variable: Variable) -> bool {
+    match node {
+        Node::Number(_) => false,
+        Node::Variable(v) => *v == variable,
+        Node::Negate(a) => depends_on(a, variable),
+        Node::Binary(_, a, b) => depends_on(a, variable) || depends_on(b, variable),
+        Node::Call(_, args) => args.iter().any(|arg| depends_on(arg, variable)),
+    }
+}
+
+impl fmt::Display for Expression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.source)
+    }
+}
+
+impl TryFrom<String> for Expression {
+    type Error = ParseError;
+
+    fn try_from(source: String) -> Result<Self, Self::Error> {
+        Self::parse(&source)
+    }
+}
+
+impl From<Expression> for String {
+    fn from(expression: Expression) -> Self {
+        expression.source
+    }
+}
+
+// Shows what went wrong together with the expression and a marker under the bad character
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+    source: String,
+    // in characters, not bytes
+    position: usize,
+    message: String,
+}
+
+impl ParseError {
+    pub fn position(&self) -> usize {
+        self.position
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} at position {}\n    {}\n    {}^",
+            self.message,
+            self.position + 1,
+            self.source,
+            " ".repeat(self.position)
+        )
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Kind {
+    Number(f64),
+    Name(String),
+    Operator(Operator),
+    Minus,
+    LeftParen,
+    RightParen,
+    Comma,
+    End,
+}
+
+#[derive(Debug, Clone)]
+struct Token {
+    kind: Kind,
+    position: usize,
+}
+
+fn tokenize(source: &str) -> Result<Vec<Token>, ParseError> {
+    let chars = source.chars().collect::<Vec<char>>();
+    let error = |position: usize, message: String| ParseError {
+        source: source.to_string(),
+        position,
+        message,
+    };
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let start = i;
+        let kind = if c.is_whitespace() {
+            i += 1;
+            continue;
+        } else if c.is_ascii_digit() || c == '.' {
+            while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '.') {
+                i += 1;
+            }
+            // only an exponent if there are digits after it, so "2e" stays 2 followed by e
+            if i < chars.len() && (chars[i] == 'e' || chars[i] == 'E') {
+                let sign = usize::from(matches!(chars.get(i + 1), Some('+' | '-')));
+                if chars.get(i + 1 + sign).is_some_and(|c| c.is_ascii_digit()) {
+                    i += 1 + sign;
+                    while i < chars.len() && chars[i].is_ascii_digit() {
+                        i += 1;
+                    }
+                }
+            }
+            let text = chars[start..i].iter().collect::<String>();
+            let value = text
+                .parse::<f64>()
+                .map_err(|_| error(start, format!("invalid number '{text}'")))?;
+            tokens.push(Token {
+                kind: Kind::Number(value),
+                position: start,
+            });
+            continue;
+        } else if c.is_alphabetic() || c == '_' {
+            while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                i += 1;
+            }
+            tokens.push(Token {
+                kind: Kind::Name(chars[start..i].iter().collect()),
+                position: start,
+            });
+            continue;
+        } else {
+            let followed_by_equals = chars.get(i + 1) == Some(&'=');
+            match c {
+                '+' => Kind::Operator(Operator::Add),
+                '-' => Kind::Minus,
+                '*' => Kind::Operator(Operator::Multiply),
+                '/' => Kind::Operator(Operator::Divide),
+                '^' => Kind::Operator(Operator::Power),
+                '<' if followed_by_equals => {
+                    i += 1;
+                    Kind::Operator(Operator::LessEqual)
+                }
+                '<' => Kind::Operator(Operator::Less),
+                '>' if followed_by_equals => {
+                    i += 1;
+                    Kind::Operator(Operator::GreaterEqual)
+                }
+                '>' => Kind::Operator(Operator::Greater),
+                '(' => Kind::LeftParen,
+                ')' => Kind::RightParen,
+                ',' => Kind::Comma,
+                _ => return Err(error(start, format!("unexpected character '{c}'"))),
+            }
+        };
+        i += 1;
+        tokens.push(Token {
+            kind,
+            position: start,
+        });
+    }
+    tokens.push(Token {
+        kind: Kind::End,
+        position: chars.len(),
+    });
+    Ok(tokens)
+}
+
+// recursive descent, where every level of precedence has its own method
+struct Parser<'a> {
+    source: &'a str,
+    tokens: Vec<Token>,
+    next: usize,
+    // whether t is one of the variables
+    time: bool,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Token {
+        self.tokens[self.next].clone()
+    }
+
+    fn advance(&mut self) -> Token {
+        let token = self.peek();
+        if token.kind != Kind::End {
+            self.next += 1;
+        }
+        token
+    }
+
+    fn error(&self, position: usize, message: impl Into<String>) -> ParseError {
+        ParseError {
+            source: self.source.to_string(),
+            position,
+            message: message.into(),
+        }
+    }
+
+    // a < b, which doesn't chain since a < b < c would compare a boolean to c
+    fn comparison(&mut self) -> Result<Node, ParseError> {
+        let left = self.sum()?;
+        match self.peek().kind {
+            Kind::Operator(
+                operator @ (Operator::Less
+                | Operator::LessEqual
+                | Operator::Greater
+                | Operator::GreaterEqual),
+            ) => {
+                self.advance();
+                let right = self.sum()?;
+                Ok(Node::Binary(operator, Box::new(left), Box::new(right)))
+            }
+            _ => Ok(left),
+        }
+    }
+
+    fn sum(&mut self) -> Result<Node, ParseError> {
+        let mut left = self.product()?;
+        loop {
+            let operator = match self.peek().kind {
+                Kind::Operator(Operator::Add) => Operator::Add,
+                Kind::Minus => Operator::Subtract,
+                _ => return Ok(left),
+            };
+            self.advance();
+            left = Node::Binary(operator, Box::new(left), Box::new(self.product()?));
+        }
+    }
+
+    fn product(&mut self) -> Result<Node, ParseError> {
+        let mut left = self.negation()?;
+        loop {
+            let operator = match self.peek().kind {
+                Kind::Operator(operator @ (Operator::Multiply | Operator::Divide)) => operator,
+                _ => return Ok(left),
+            };
+            self.advance();
+            left = Node::Binary(operator, Box::new(left), Box::new(self.negation()?));
+        }
+    }
+
+    fn negation(&mut self) -> Result<Node, ParseError> {
+        if self.peek().kind == Kind::Minus {
+            self.advance();
+            return Ok(Node::Negate(Box::new(self.negation()?)));
+        }
+        self.power()
+    }
+
+    fn power(&mut self) -> Result<Node, ParseError> {
+        let base = self.atom()?;
+        if self.peek().kind == Kind::Operator(Operator::Power) {
+            self.advance();
+            // the exponent may have its own sign, as in e^-x
+            let exponent = self.negation()?;
+            return Ok(Node::Binary(
+                Operator::Power,
+                Box::new(base),
+                Box::new(exponent),
+            ));
+        }
+        Ok(base)
+    }
+
+    fn atom(&mut self) -> Result<Node, ParseError> {
+        let token = self.advance();
+        match token.kind {
+            Kind::Number(value) => Ok(Node::Number(value)),
+            Kind::LeftParen => {
+                let inner = self.comparison()?;
+                self.expect_closing(token.position)?;
+                Ok(inner)
+            }
+            Kind::Name(name) => {
+                if self.peek().kind == Kind::LeftParen {
+                    return self.call(&name, token.position);
+                }
+                match name.as_str() {
+                    "x" => Ok(Node::Variable(Variable::X)),
+                    "y" => Ok(Node::Variable(Variable::Y)),
+                    "t" if self.time => Ok(Node::Variable(Variable::T)),
+                    "t" => Err(self.error(
+                        token.position,
+                        "t is only allowed in the driving terms of a potential",
+                    )),
+                    "pi" => Ok(Node::Number(PI)),
+                    "e" => Ok(Node::Number(E)),
+                    _ => Err(self.error(
+                        token.position,
+                        format!("unknown variable '{name}', expected x, y, t, pi or e"),
+                    )),
+                }
+            }
+            Kind::End => Err(self.error(token.position, "unexpected end of expression")),
+            _ => Err(self.error(
+                token.position,
+                "expected a number, variable, function or '('",
+            )),
+        }
+    }
+
+    fn call(&mut self, name: &str, position: usize) -> Result<Node, ParseError> {
+        let function = Function::from_name(name)
+            .ok_or_else(|| self.error(position, format!("unknown function '{name}'")))?;
+        let open = self.advance().position;
+        let mut args = vec![self.comparison()?];
+        while self.peek().kind == Kind::Comma {
+            self.advance();
+            args.push(self.comparison()?);
+        }
+        self.expect_closing(open)?;
+
+        if args.len() != function.arity() {
+            return Err(self.error(
+                position,
+                format!(
+                    "{name} takes {} argument{}, got {}",
+                    function.arity(),
+                    if function.arity() == 1 { "" } else { "s" },
+                    args.len()
+                ),
+            ));
+        }
+        Ok(Node::Call(function, args))
+    }
+
+    // points at the opening parenthesis if the closing one is missing altogether
+    fn expect_closing(&mut self, open: usize) -> Result<(), ParseError> {
+        let token = self.advance();
+        match token.kind {
+            Kind::RightParen => Ok(()),
+            Kind::End => Err(self.error(open, "unclosed '('")),
+            _ => Err(self.error(token.position, "expected ')'")),
+        }
+    }
+}
@@ -0,0 +1,132 @@
+use std::f64::consts::PI;
+
+use nalgebra::DVector;
+use num_traits::Zero;
+
+use crate::complex::{i, Complex, Real};
+
+// Discrete Fourier transform X_k = sum_j x_j e^(-2*pi*i*j*k/n), without any normalization.
+pub fn fft<T: Real>(data: &DVector<Complex<T>>) -> DVector<Complex<T>> {
+    DVector::from(transform(data.as_slice(), -1.))
+}
+
+// Inverse of fft, i.e. x_j = 1/n sum_k X_k e^(2*pi*i*j*k/n)
+pub fn ifft<T: Real>(data: &DVector<Complex<T>>) -> DVector<Complex<T>> {
+    let n = T::of(data.len() as f64);
+    DVector::from(transform(data.as_slice(), 1.)).map(|x| x / n)
+}
+
+// The angular wave numbers belonging to each entry of the fft of n points spaced by d.
+// Follows the usual ordering: zero first, then the positive and finally the negative values.
+pub fn fft_frequencies(n: usize, d: f64) -> Vec<f64> {
+    let dk = 2. * PI / (n as f64 * d);
+    (0..n)
+        .map(|j| {
+            if j <= (n - 1) / 2 {
+                j as f64 * dk
+            } else {
+                (j as f64 - n as f64) * dk
+            }
+        })
+        .collect()
+}
+
+// sign is -1 for the forward transform and 1 for the backward one
+fn transform<T: Real>(data: &[Complex<T>], sign: f64) -> Vec<Complex<T>> {
+    let n = data.len();
+    if n <= 1 {
+        return data.to_vec();
+    }
+
+    // e^(sign*2*pi*i*j/n), shared by every level of the recursion.
+    // Always taken in f64, so an f32 transform only loses precision in the sums.
+    let twiddles = (0..n)
+        .map(|j| Complex::exp(i() * (sign * 2. * PI * j as f64 / n as f64)).cast())
+        .collect::<Vec<Complex<T>>>();
+
+    if n.is_power_of_two() {
+        radix_2(data, &twiddles)
+    } else {
+        mixed_radix(data, &twiddles, 1)
+    }
+}
+
+// Iterative in-place Cooley-Tukey for lengths that are powers of two
+fn radix_2<T: Real>(data: &[Complex<T>], twiddles: &[Complex<T>]) -> Vec<Complex<T>> {
+    let n = data.len();
+    let bits = n.trailing_zeros();
+
+    // bit reversed ordering of the input
+    let mut res = vec![Complex::zero(); n];
+    for (j, x) in data.iter().enumerate() {
+        res[j.reverse_bits() >> (usize::BITS - bits)] = *x;
+    }
+
+    let mut len = 2;
+    while len <= n {
+        let step = n / len;
+        for start in (0..n).step_by(len) {
+            for j in 0..len / 2 {
+                let t = twiddles[j * step] * res[start + j + len / 2];
+                let u = res[start + j];
+                res[start + j] = u + t;
+                res[start + j + len / 2] = u - t;
+            }
+        }
+        len *= 2;
+    }
+
+    res
+}
+
+// Recursive Cooley-Tukey that splits off the smallest prime factor p of the length on every
+// level. The p interleaved sub-sequences are transformed separately and then combined.
+// "stride" is how far apart the twiddle factors of the current length are in the full table.
+// Prime lengths fall back on the direct O(n^2) sum, so this is only fast for lengths with
+// small factors, which all of the grids in use have.
+fn mixed_radix<T: Real>(
+    data: &[Complex<T>],
+    twiddles: &[Complex<T>],
+    stride: usize,
+) -> Vec<Complex<T>> {
+    let n = data.len();
+    if n == 1 {
+        return data.to_vec();
+    }
+    let p = smallest_factor(n);
+    let m = n / p;
+
+    let subs: Vec<Vec<Complex<T>>> = if p == n {
+        // prime length, so every sub-sequence is a single value
+        data.iter().map(|x| vec![*x]).collect()
+    } else {
+        (0..p)
+            .map(|r| {
+                let sub = data.iter().skip(r).step_by(p).copied().collect::<Vec<_>>();
+                mixed_radix(&sub, twiddles, stride * p)
+            })
+            .collect()
+    };
+
+    let full = twiddles.len();
+    (0..n)
+        .map(|k| {
+            let mut sum = Complex::zero();
+            for (r, sub) in subs.iter().enumerate() {
+                sum += twiddles[(r * k * stride) % full] * sub[k % m];
+            }
+            sum
+        })
+        .collect()
+}
+
+fn smallest_factor(n: usize) -> usize {
+    let mut p = 2;
+    while p * p <= n {
+        if n % p == 0 {
+            return p;
+        }
+        p += 1;
+    }
+    n
+}
@@ -0,0 +1,279 @@
+use std::{
+    f64::consts::E,
+    io::{Error, ErrorKind},
+};
+
+use nalgebra::{DMatrix, DVector};
+use num_traits::Zero;
+
+use crate::{
+    complex::*,
+    one_dim::{eigen::lowest_states, iteration::potential_vector},
+    potential::Potential,
+    quadrature::simpson,
+    scenario::{Component, InitialState, Scenario, Shape},
+};
+
+// Builds the initial wave function as a superposition of parts. Each part is sampled onto the
+// grid and normalized on its own, then multiplied by its amplitude, and finally the whole sum
+// is normalized again. The same builder can be sampled onto the 1D and the 2D grid, e.g.
+//
+// let psi = WaveBuilder::new()
+//     .gaussian(-2., 0.3, 10.)
+//     .function(i(), |x| Complex::exp(i() * (-10. * x)))
+//     .build_1d(&s);
+pub struct WaveBuilder {
+    parts: Vec<(Complex, Part)>,
+}
+
+enum Part {
+    Shape(Shape),
+    PlaneWaves(InitialState),
+    Function(Box<dyn Fn(f64) -> Complex>),
+    Function2d(Box<dyn Fn(f64, f64) -> Complex>),
+}
+
+impl WaveBuilder {
+    pub fn new() -> Self {
+        Self { parts: Vec::new() }
+    }
+
+    // the components described by the scenario, or the superposition of plane waves
+    // from its k values if it doesn't have any
+    pub fn from_scenario(s: &Scenario) -> Self {
+        if s.initial.components.is_empty() {
+            return Self::new().plane_waves(&s.initial);
+        }
+        s.initial
+            .components
+            .iter()
+            .cloned()
+            .fold(Self::new(), |builder, c| builder.component(c))
+    }
+
+    pub fn component(mut self, component: Component) -> Self {
+        let amplitude = component.amplitude * Complex::exp(i() * component.phase);
+        self.parts.push((amplitude, Part::Shape(component.shape)));
+        self
+    }
+
+    // int{c(k)e^(ikx)}dk as described in InitialState, in two dimensions the same along z
+    pub fn plane_waves(mut self, spec: &InitialState) -> Self {
+        self.parts
+            .push((Complex::from_real(1.), Part::PlaneWaves(spec.clone())));
+        self
+    }
+
+    // Fails on a function of x and z, an eigenstate the grid doesn't have, and parts that are
+    // zero on the grid or cancel out
+    pub fn build_1d(&self, s: &Scenario) -> Result<DVector<Complex>, Error> {
+        let points = s.grid.points();
+        let mut psi = DVector::from(vec![Complex::zero(); points.len()]);
+        for (amplitude, part) in &self.parts {
+            let values = match part {
+                Part::Shape(Shape::Gaussian {
+                    x_0, sigma, k_0, ..
+                }) => sample(&points, |x| gaussian(x, *x_0, *sigma, *k_0)),
+                Part::Shape(Shape::Eigenstate { n, .. }) => {
+                    eigenstate(*n, &potential_vector(s), s)?
+                }
+                Part::PlaneWaves(spec) => sample(&points, |x| plane_waves(x, spec)),
+                Part::Function(f) => sample(&points, f),
+                Part::Function2d(_) => {
+                    return Err(Error::new(
+                        ErrorKind::InvalidInput,
+                        "A function of x and z can't be sampled onto the 1D grid",
+                    ))
+                }
+            };
+            psi += *amplitude * normalize_1d(values, 0., s)?;
+        }
+        normalize_1d(psi, self.largest_norm(), s)
+    }
+
+    // Rows are along x and columns along z. Fails just like build_1d, on a function of x alone,
+    // and on eigenstates of a potential that isn't V(x) + V_z(z).
+    pub fn build_2d(&self, s: &Scenario) -> Result<DMatrix<Complex>, Error> {
+        let points = s.grid.points();
+        let n = points.len();
+        let mut psi = DMatrix::from_element(n, n, Complex::zero());
+        for (amplitude, part) in &self.parts {
+            let values = match part {
+                Part::Shape(Shape::Gaussian {
+                    x_0,
+                    sigma,
+                    k_0,
+                    z_0,
+                    k_z,
+                }) => outer(
+                    &sample(&points, |x| gaussian(x, *x_0, *sigma, *k_0)),
+                    &sample(&points, |z| gaussian(z, *z_0, *sigma, *k_z)),
+                ),
+                Part::Shape(Shape::Eigenstate { n, n_z }) => {
+                    // For V(x) + V_z(z) the eigenstates are products of the eigenstates along
+                    // each axis. Just like in 1D, only the real part of the potential counts,
+                    // so they are the states without the absorbing layer.
+                    if !s.potential.is_separable() {
+                        return Err(Error::new(
+                            ErrorKind::InvalidInput,
+                            "Eigenstates in two dimensions need a potential of the form \
+                             V(x) + V_z(z), but a formula mixes x and y",
+                        ));
+                    }
+                    let along_z = sample(&points, |z| s.potential.value_z(z).into());
+                    outer(
+                        &eigenstate(*n, &potential_vector(s), s)?,
+                        &eigenstate(*n_z, &along_z, s)?,
+                    )
+                }
+                Part::PlaneWaves(spec) => {
+                    let along = sample(&points, |x| plane_waves(x, spec));
+                    outer(&along, &along)
+                }
+                Part::Function2d(f) => DMatrix::from_fn(n, n, |j, k| f(points[j], points[k])),
+                Part::Function(_) => {
+                    return Err(Error::new(
+                        ErrorKind::InvalidInput,
+                        "A function of x alone can't be sampled onto the 2D grid",
+                    ))
+                }
+            };
+            psi += *amplitude * &normalize_2d(values, 0., s)?;
+        }
+        normalize_2d(psi, self.largest_norm(), s)
+    }
+
+    // every part is normalized, so the norm of the sum is at most (sum of |amplitude|)^2
+    fn largest_norm(&self) -> f64 {
+        self.parts
+            .iter()
+            .map(|(amplitude, _)| amplitude.abs())
+            .sum::<f64>()
+            .powi(2)
+    }
+}
+
+// shorthands for building the initial state in code rather than from a scenario
+impl WaveBuilder {
+    // a gaussian packet around x_0 with width sigma, moving with k_0
+    pub fn gaussian(self, x_0: f64, sigma: f64, k_0: f64) -> Self {
+        self.component(Component::new(Shape::Gaussian {
+            x_0,
+            sigma,
+            k_0,
+            z_0: 0.,
+            k_z: 0.,
+        }))
+    }
+
+    // the stationary state number n of the scenario's Hamiltonian
+    pub fn eigenstate(self, n: usize) -> Self {
+        self.component(Component::new(Shape::Eigenstate { n, n_z: 0 }))
+    }
+
+    // any function of x, only for the 1D grid
+    pub fn function(mut self, amplitude: Complex, f: impl Fn(f64) -> Complex + 'static) -> Self {
+        self.parts.push((amplitude, Part::Function(Box::new(f))));
+        self
+    }
+
+    // any function of x and z, only for the 2D grid
+    pub fn function_2d(
+        mut self,
+        amplitude: Complex,
+        f: impl Fn(f64, f64) -> Complex + 'static,
+    ) -> Self {
+        self.parts.push((amplitude, Part::Function2d(Box::new(f))));
+        self
+    }
+}
+
+impl Default for WaveBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn sample(points: &[f64], f: impl Fn(f64) -> Complex) -> DVector<Complex> {
+    DVector::from(points.iter().map(|x| f(*x)).collect::<Vec<Complex>>())
+}
+
+fn gaussian(x: f64, x_0: f64, sigma: f64, k_0: f64) -> Complex {
+    E.powf(-(x - x_0).powi(2) / (4. * sigma.powi(2))) * Complex::exp(i() * (k_0 * x))
+}
+
+fn plane_waves(x: f64, spec: &InitialState) -> Complex {
+    let k_0 = spec.k_0;
+    // c(k) = e^(-(k-k_0)/delta_k)^2
+    let c_k = |k: f64| E.powf(-((k - k_0) / spec.delta_k).powi(2));
+
+    let mut res = Complex::zero();
+    for n in (k_0.round() as isize - spec.k_range)..=(k_0.round() as isize + spec.k_range) {
+        let k = n as f64 * spec.dk;
+        res += c_k(k) * Complex::exp(i() * (k * x));
+    }
+    res
+}
+
+// the grid has as many stationary states as it has points
+fn eigenstate(
+    n: usize,
+    potential: &DVector<Complex>,
+    s: &Scenario,
+) -> Result<DVector<Complex>, Error> {
+    if n >= potential.len() {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            format!(
+                "There is no eigenstate {n}, the grid only has {} of them",
+                potential.len()
+            ),
+        ));
+    }
+    Ok(lowest_states(n + 1, potential, s).swap_remove(n).psi)
+}
+
+// psi(x, z) = a(x) b(z)
+fn outer(a: &DVector<Complex>, b: &DVector<Complex>) -> DMatrix<Complex> {
+    DMatrix::from_fn(a.len(), b.len(), |j, k| a[j] * b[k])
+}
+
+// 1/sqrt(norm), unless psi is zero on the grid. Rounding errors are all that's left when psi
+// cancelled out, so anything tiny compared to the largest norm psi could have counts as zero.
+fn inverse_root(norm: f64, largest: f64) -> Result<f64, Error> {
+    if norm.is_finite() && norm > 0. && norm > largest * 1e-20 {
+        Ok(1. / norm.sqrt())
+    } else {
+        Err(Error::new(
+            ErrorKind::InvalidInput,
+            "The initial state is zero on the grid, so it can't be normalized",
+        ))
+    }
+}
+
+fn normalize_1d(
+    psi: DVector<Complex>,
+    largest: f64,
+    s: &Scenario,
+) -> Result<DVector<Complex>, Error> {
+    let density = psi.iter().map(|p| p.abs_squared()).collect::<Vec<f64>>();
+    let factor = inverse_root(simpson(&density, s.grid.spacing), largest)?;
+    Ok(psi.map(|p| p * factor))
+}
+
+fn normalize_2d(
+    psi: DMatrix<Complex>,
+    largest: f64,
+    s: &Scenario,
+) -> Result<DMatrix<Complex>, Error> {
+    // integrate along z for every x first, then along x
+    let along_z = psi
+        .row_iter()
+        .map(|row| {
+            let density = row.iter().map(|p| p.abs_squared()).collect::<Vec<f64>>();
+            simpson(&density, s.grid.spacing)
+        })
+        .collect::<Vec<f64>>();
+    let factor = inverse_root(simpson(&along_z, s.grid.spacing), largest)?;
+    Ok(psi.map(|p| p * factor))
+}
@@ -0,0 +1,39 @@
+// The physics behind the playground, usable from other crates. Grid, WaveFunction, Hamiltonian,
+// Propagator and Simulation are the entry points, everything they take or return is public as
+// well. The finite difference details in one_dim and two_dim are not, so they can change
+// without breaking anyone.
+use std::io::{Error, ErrorKind};
+
+pub mod checkpoint;
+pub mod complex;
+mod config;
+pub mod export;
+pub mod expression;
+mod fft;
+pub mod initial;
+mod one_dim;
+pub mod potential;
+pub mod quadrature;
+pub mod scenario;
+mod simulation;
+#[cfg(test)]
+mod test;
+mod two_dim;
+mod utils;
+
+pub use complex::{Complex, Real};
+pub use config::{Config, Precision, ScatteringMode};
+pub use scenario::{Grid, Integrator, Scenario};
+pub use simulation::{Hamiltonian, Propagator, Simulation, WaveFunction};
+
+// runs whatever the command line asked for, in the visual or the headless mode
+pub fn run(cfg: &Config) -> Result<(), Error> {
+    match cfg.dims() {
+        1 => one_dim::run(cfg),
+        2 => two_dim::run(cfg),
+        dims => Err(Error::new(
+            ErrorKind::InvalidInput,
+            format!("Only one and two dimensions are supported, got {dims}"),
+        )),
+    }
+}
@@ -0,0 +1,152 @@
+#![allow(non_snake_case)]
+use nalgebra::DVector;
+
+use super::{inner_product, iteration::descrete_derivative_matrix, StationaryState};
+use crate::{complex::Complex, scenario::Scenario, utils::solve_tridiagonal};
+
+// bisection stops once the interval is this small relative to the spectrum
+const BISECTION_TOLERANCE: f64 = 1e-14;
+const INVERSE_ITERATIONS: usize = 3;
+
+// Finds the "count" lowest eigenstates of the finite difference Hamiltonian directly.
+// H is real, symmetric and tridiagonal (only the real part of the potential is used), so
+// every eigenvalue is located by bisection using Sturm sequences, and the corresponding
+// eigenvector is found with a few steps of inverse iteration. The eigenvectors are
+// normalized such that <psi|psi> = 1, see inner_product.
+pub fn lowest_states(
+    count: usize,
+    potential: &DVector<Complex>,
+    s: &Scenario,
+) -> Vec<StationaryState> {
+    if s.boundary.periodic {
+        return lowest_states_dense(count, potential, s);
+    }
+    let (diag, off) = tridiagonal_hamiltonian(potential, s);
+    let count = count.min(diag.len());
+
+    // Gershgorin circles, every eigenvalue lies within these bounds
+    let lower = diag.iter().copied().fold(f64::INFINITY, f64::min) - 2. * off.abs();
+    let upper = diag.iter().copied().fold(f64::NEG_INFINITY, f64::max) + 2. * off.abs();
+
+    (0..count)
+        .map(|k| {
+            let energy = bisect(k, &diag, off, lower, upper);
+            // a tiny shift so that the system is not exactly singular
+            let shift = energy - 1e-10 * energy.abs().max(1.);
+            StationaryState {
+                energy,
+                psi: inverse_iteration(shift, &diag, off, s),
+            }
+        })
+        .collect()
+}
+
+// Periodic boundaries add corners to H, which breaks the Sturm sequences (and makes
+// degenerate pairs of states common), so the full matrix is diagonalized instead.
+fn lowest_states_dense(
+    count: usize,
+    potential: &DVector<Complex>,
+    s: &Scenario,
+) -> Vec<StationaryState> {
+    let size = s.grid.points().len();
+    let mut H = descrete_derivative_matrix(size, s).map(|x| x.real());
+    for (j, v) in potential.iter().enumerate() {
+        H[(j, j)] += v.real();
+    }
+    let eigen = H.symmetric_eigen();
+
+    let mut order = (0..size).collect::<Vec<usize>>();
+    order.sort_by(|a, b| eigen.eigenvalues[*a].total_cmp(&eigen.eigenvalues[*b]));
+    order
+        .into_iter()
+        .take(count)
+        .map(|k| {
+            let psi = eigen.eigenvectors.column(k).map(Complex::from_real);
+            let norm = inner_product(&psi, &psi, s).real().sqrt();
+            StationaryState {
+                energy: eigen.eigenvalues[k],
+                psi: psi / Complex::from_real(norm),
+            }
+        })
+        .collect()
+}
+
+// The coefficients c_n = <psi_n|psi> of psi in the basis of the given states,
+// so that psi = sum{c_n psi_n} as long as the states span psi
+pub fn expand(psi: &DVector<Complex>, states: &[StationaryState], s: &Scenario) -> Vec<Complex> {
+    states
+        .iter()
+        .map(|state| {
+            inner_product(&state.psi, psi, s)
+                / inner_product(&state.psi, &state.psi, s).real().sqrt()
+        })
+        .collect()
+}
+
+// diagonal and (constant) off diagonal of H
+fn tridiagonal_hamiltonian(potential: &DVector<Complex>, s: &Scenario) -> (Vec<f64>, f64) {
+    let kinetic = s.constants.h_bar().powi(2) / (2. * s.constants.m * s.grid.spacing.powi(2));
+    let diag = potential.iter().map(|v| 2. * kinetic + v.real()).collect();
+    (diag, -kinetic)
+}
+
+// The number of eigenvalues smaller than lambda. The pivots of the LDL^T decomposition of
+// H - lambda form a Sturm sequence, and the number of negative pivots is that count.
+fn sturm_count(lambda: f64, diag: &[f64], off: f64) -> usize {
+    let mut count = 0;
+    let mut q = 1.;
+    for (i, d) in diag.iter().enumerate() {
+        q = if i == 0 {
+            d - lambda
+        } else {
+            d - lambda - off.powi(2) / q
+        };
+        if q == 0. {
+            // avoid dividing by zero, the sign is all that matters
+            q = f64::EPSILON * off.abs();
+        }
+        if q < 0. {
+            count += 1;
+        }
+    }
+    count
+}
+
+// the k-th smallest eigenvalue (counting from zero)
+fn bisect(k: usize, diag: &[f64], off: f64, mut lower: f64, mut upper: f64) -> f64 {
+    let tolerance = BISECTION_TOLERANCE * (upper - lower).abs().max(1.);
+    while upper - lower > tolerance {
+        let middle = 0.5 * (lower + upper);
+        if sturm_count(middle, diag, off) > k {
+            upper = middle;
+        } else {
+            lower = middle;
+        }
+    }
+    0.5 * (lower + upper)
+}
+
+// Solving (H - shift) x = b repeatedly amplifies the component along the eigenvector with
+// the energy closest to shift, since it has by far the smallest eigenvalue of H - shift.
+// H - shift may still be singular, in which case solve_tridiagonal meets a zero pivot.
+pub fn inverse_iteration(shift: f64, diag: &[f64], off: f64, s: &Scenario) -> DVector<Complex> {
+    let n = diag.len();
+    let shifted = diag
+        .iter()
+        .map(|d| Complex::from_real(d - shift))
+        .collect::<Vec<Complex>>();
+    let off = vec![Complex::from_real(off); n - 1];
+
+    // any start works as long as it is not orthogonal to the eigenvector, which a symmetric
+    // start would be for the odd states of a symmetric potential
+    let mut psi = DVector::from(
+        (0..n)
+            .map(|j| Complex::from_real((j as f64 + 1.).sqrt()))
+            .collect::<Vec<Complex>>(),
+    );
+    for _ in 0..INVERSE_ITERATIONS {
+        psi = DVector::from(solve_tridiagonal(&off, &shifted, &off, psi.as_slice()));
+        psi /= Complex::from_real(inner_product(&psi, &psi, s).real().sqrt());
+    }
+    psi
+}
@@ -0,0 +1,215 @@
+use std::io::Error;
+
+use nalgebra::DVector;
+
+use super::{
+    eigen::{expand, lowest_states},
+    imaginary_time::stationary_states,
+    iteration::{potential_at, potential_vector},
+    observables::Observables,
+    scattering::{packet_transmission, scatter, transmission, Region},
+    wave,
+};
+use crate::{
+    complex::*,
+    export::{Exporter, Frame},
+    scenario::Scenario,
+    simulation::Simulation,
+    Config, Precision, ScatteringMode,
+};
+
+// imaginary time propagation stops once the energy changes by less than this
+const IMAGINARY_TOLERANCE: f64 = 1e-10;
+const IMAGINARY_MAX_STEPS: usize = 1_000_000;
+// the packet has scattered once less than this is left close to the potential
+const SCATTERING_TOLERANCE: f64 = 1e-4;
+// used unless the number of steps is given explicitly
+const SCATTERING_MAX_STEPS: usize = 100_000;
+// number of energies T(E) is shown for
+const SPECTRUM_POINTS: usize = 200;
+
+// Runs the simulation without bevy for the number of steps given by the config,
+// printing the observables every interval steps (and after the last one).
+pub fn run(cfg: &Config) -> Result<(), Error> {
+    let s = cfg.scenario();
+    if let Some(count) = cfg.imaginary_time() {
+        return print_stationary_states(count, s);
+    }
+    if let Some(count) = cfg.eigenstates() {
+        return print_spectrum(count, s);
+    }
+    if let Some(mode) = cfg.scattering() {
+        let max_steps = cfg.given_steps(s.time.dt).unwrap_or(SCATTERING_MAX_STEPS);
+        return match mode {
+            ScatteringMode::Packet => print_scattering(max_steps, s),
+            ScatteringMode::Spectrum => print_transmission(s),
+        };
+    }
+
+    match cfg.precision() {
+        Precision::Double => evolve::<f64>(cfg),
+        Precision::Single => evolve::<f32>(cfg),
+    }
+}
+
+// the time evolution itself, stepping psi in T but measuring it in f64
+fn evolve<T: Real>(cfg: &Config) -> Result<(), Error> {
+    // a checkpoint picks up at the step it was saved at, so the same number of
+    // steps finishes the run it came from
+    let mut simulation = match cfg.resume() {
+        Some(checkpoint) => Simulation::<T>::from_checkpoint(checkpoint)?,
+        None => Simulation::<T>::new(cfg.scenario().clone(), 1)?,
+    };
+    let s = cfg.scenario();
+    let potential = potential_vector(s);
+    let steps = cfg.steps(s.time.dt);
+    let psi = |simulation: &Simulation<T>| {
+        // the simulation is always one dimensional here
+        let psi = simulation.wave_function().cast::<f64>();
+        psi.as_1d().unwrap().clone()
+    };
+
+    print!(
+        "{:>10} {:>12} {:>12} {:>12} {:>12} {:>12} {:>12}",
+        "step", "time", "norm", "<x>", "<p>", "dx*dp", "energy"
+    );
+    // with an absorbing boundary, the probability lost at the edges (since the start or
+    // the checkpoint) is shown as well
+    let initial_norm = s
+        .boundary
+        .absorbing
+        .is_some()
+        .then(|| Observables::compute(&psi(&simulation), &potential, s).norm);
+    if initial_norm.is_some() {
+        print!(" {:>12}", "absorbed");
+    }
+    println!();
+
+    let start = simulation.step();
+    print_observables(start, &psi(&simulation), &potential, initial_norm, s);
+    export(cfg.exporter(), start, steps, &simulation)?;
+    while simulation.step() < steps {
+        simulation.advance();
+        let step = simulation.step();
+        if step % cfg.interval() == 0 || step == steps {
+            print_observables(step, &psi(&simulation), &potential, initial_norm, s);
+            if let Some(path) = cfg.checkpoint() {
+                simulation.checkpoint().save(path)?;
+            }
+        }
+        export(cfg.exporter(), step, steps, &simulation)?;
+    }
+    Ok(())
+}
+
+// writes the frame after step if the exporter asks for it
+fn export<T: Real>(
+    exporter: Option<&Exporter>,
+    step: usize,
+    last: usize,
+    simulation: &Simulation<T>,
+) -> Result<(), Error> {
+    let Some(exporter) = exporter.filter(|e| e.wants(step, last)) else {
+        return Ok(());
+    };
+    let psi = simulation.wave_function().cast::<f64>();
+    let points = simulation.grid().points();
+    let frame = Frame::from_1d(step, simulation.time(), &points, psi.as_1d().unwrap());
+    exporter.write(&frame)?;
+    Ok(())
+}
+
+// finds the lowest states with imaginary time propagation and prints their energies
+fn print_stationary_states(count: usize, s: &Scenario) -> Result<(), Error> {
+    let states = stationary_states(count, s, IMAGINARY_TOLERANCE, IMAGINARY_MAX_STEPS)?;
+    println!("{:>10} {:>12}", "state", "energy");
+    for (n, state) in states.iter().enumerate() {
+        println!("{:>10} {:>12.6}", n, state.energy);
+    }
+    Ok(())
+}
+
+// prints the lowest energies of the Hamiltonian together with the probability of finding
+// the initial wave packet in each of the corresponding states
+fn print_spectrum(count: usize, s: &Scenario) -> Result<(), Error> {
+    let states = lowest_states(count, &potential_vector(s), s);
+    let coefficients = expand(&wave(s)?.1, &states, s);
+
+    println!("{:>10} {:>12} {:>12}", "state", "energy", "|c_n|^2");
+    for (n, (state, c_n)) in states.iter().zip(coefficients).enumerate() {
+        println!(
+            "{:>10} {:>12.6} {:>12.6}",
+            n,
+            state.energy,
+            c_n.abs_squared()
+        );
+    }
+    Ok(())
+}
+
+// runs the initial packet into the potential and prints how it was split up, together with
+// what the stationary T(E) predicts for the packet's distribution of energies
+fn print_scattering(max_steps: usize, s: &Scenario) -> Result<(), Error> {
+    let psi = wave(s)?.1;
+    let potential = potential_vector(s);
+    let expected = packet_transmission(&psi, &potential, s)?;
+    let result = scatter(&psi, &potential, s, SCATTERING_TOLERANCE, max_steps)?;
+
+    println!(
+        "{:>12} {:>12} {:>12} {:>12} {:>12}",
+        "time", "reflected", "transmitted", "sum", "T(E)"
+    );
+    println!(
+        "{:>12.6} {:>12.6} {:>12.6} {:>12.6} {:>12.6}",
+        result.time,
+        result.reflected,
+        result.transmitted,
+        result.sum(),
+        expected
+    );
+    Ok(())
+}
+
+// T(E) and R(E) = 1 - T(E) up to twice the energy of the packet or the top of the potential
+fn print_transmission(s: &Scenario) -> Result<(), Error> {
+    let potential = potential_vector(s);
+    let region = Region::find(&potential)?;
+    let top = (region.start..region.end)
+        .map(|j| potential[j].real())
+        .fold(f64::MIN, f64::max);
+    let o = Observables::compute(&wave(s)?.1, &potential, s);
+    let max_energy = 2. * top.max(o.kinetic + potential[0].real());
+
+    println!("{:>12} {:>12} {:>12}", "energy", "T(E)", "R(E)");
+    for n in 1..=SPECTRUM_POINTS {
+        let energy = max_energy * n as f64 / SPECTRUM_POINTS as f64;
+        let t = transmission(energy, &potential, s)?;
+        println!("{:>12.6} {:>12.6} {:>12.6}", energy, t, 1. - t);
+    }
+    Ok(())
+}
+
+fn print_observables(
+    step: usize,
+    psi: &DVector<Complex>,
+    potential: &DVector<Complex>,
+    initial_norm: Option<f64>,
+    s: &Scenario,
+) {
+    let time = step as f64 * s.time.dt;
+    let o = Observables::compute(psi, &potential_at(potential, time, s), s);
+    print!(
+        "{:>10} {:>12.6} {:>12.6} {:>12.6} {:>12.6} {:>12.6} {:>12.6}",
+        step,
+        time,
+        o.norm,
+        o.x,
+        o.p,
+        o.uncertainty(),
+        o.energy()
+    );
+    if let Some(initial_norm) = initial_norm {
+        print!(" {:>12.6}", initial_norm - o.norm);
+    }
+    println!();
+}
@@ -0,0 +1,88 @@
+use std::io::Error;
+
+use nalgebra::DVector;
+
+use super::{
+    inner_product,
+    iteration::{hamiltonian, potential_vector, Stencil},
+    normalize, wave, StationaryState,
+};
+use crate::{complex::Complex, scenario::Scenario, utils::Rk4Workspace};
+
+// how many steps are taken between each check for convergence
+const CHECK_INTERVAL: usize = 100;
+
+// Substituting tau = it turns the Schrödinger equation into d(psi)/d(tau) = -H psi / hbar.
+// Every eigenstate then decays as e^(-E*tau/hbar), so after renormalizing only the
+// state with the lowest energy survives. The step itself is RK4, just like rk4_step.
+pub fn imaginary_step(
+    psi: &mut DVector<Complex>,
+    potential: &DVector<Complex>,
+    s: &Scenario,
+    workspace: &mut Rk4Workspace,
+) {
+    let stencil = Stencil::new(potential, false, s);
+    workspace.step(
+        psi.as_mut_slice(),
+        0.,
+        s.time.dt,
+        Complex::from_real(-s.time.dt / s.constants.h_bar()),
+        |f, j, _| stencil.apply(f, j, 0.),
+    );
+}
+
+// Finds the "count" lowest stationary states of the scenario's Hamiltonian, starting from
+// the initial wave packet. Excited states are found by removing the components along
+// all of the lower states after every step (Gram-Schmidt), so they can only decay to the
+// lowest state that is left. Each state is propagated until the energy changes by less than
+// tolerance over CHECK_INTERVAL steps, or until max_steps have been taken.
+pub fn stationary_states(
+    count: usize,
+    s: &Scenario,
+    tolerance: f64,
+    max_steps: usize,
+) -> Result<Vec<StationaryState>, Error> {
+    let potential = potential_vector(s);
+    let mut states: Vec<StationaryState> = Vec::new();
+    let mut workspace = Rk4Workspace::default();
+    for _ in 0..count {
+        let mut psi = wave(s)?.1;
+        project_out(&mut psi, &states, s);
+        let mut energy = energy_expectation(&psi, &potential, s);
+
+        for step in 1..=max_steps {
+            imaginary_step(&mut psi, &potential, s, &mut workspace);
+            project_out(&mut psi, &states, s);
+            psi = DVector::from(normalize(psi.as_slice().to_vec(), s));
+
+            if step % CHECK_INTERVAL == 0 {
+                let next_energy = energy_expectation(&psi, &potential, s);
+                let converged = (next_energy - energy).abs() < tolerance;
+                energy = next_energy;
+                if converged {
+                    break;
+                }
+            }
+        }
+
+        states.push(StationaryState {
+            energy: energy_expectation(&psi, &potential, s),
+            psi,
+        });
+    }
+    Ok(states)
+}
+
+// Gram-Schmidt, removes the components of psi along each of the states
+fn project_out(psi: &mut DVector<Complex>, states: &[StationaryState], s: &Scenario) {
+    for state in states {
+        let overlap = inner_product(&state.psi, psi, s) / inner_product(&state.psi, &state.psi, s);
+        *psi -= overlap * &state.psi;
+    }
+}
+
+// <psi|H|psi> / <psi|psi>
+fn energy_expectation(psi: &DVector<Complex>, potential: &DVector<Complex>, s: &Scenario) -> f64 {
+    inner_product(psi, &hamiltonian(psi, potential, s), s).real()
+        / inner_product(psi, psi, s).real()
+}
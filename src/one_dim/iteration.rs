@@ -1,45 +1,280 @@
 #![allow(non_snake_case)]
-use super::{v, DT, H_BAR, L, M, POTENTIAL};
-use crate::complex::*;
+use super::v;
+use crate::{
+    complex::*,
+    fft::{fft, fft_frequencies, ifft},
+    potential::TimeDependent,
+    scenario::{Integrator, Scenario},
+    utils::{for_each_chunk, solve_cyclic_tridiagonal, solve_tridiagonal, Rk4Workspace},
+};
 use nalgebra::{DMatrix, DVector};
 use num_traits::{One, Zero};
+use std::borrow::Cow;
 
-use super::DX;
-pub fn rk4_iter_dt(psi0: &DVector<Complex>) -> DVector<Complex> {
-    let k1 = d_dt(&psi0);
-    let k2 = d_dt(&(psi0 + Complex::from_real(0.5) * &k1));
-    let k3 = d_dt(&(psi0 + Complex::from_real(0.5) * &k2));
-    let k4 = d_dt(&(psi0 + &k3));
+// Advances psi0 from t to t + dt using the integrator selected in the scenario.
+// The potential is the one from potential_vector, which only has to be sampled once
+// for the whole simulation rather than once per step. Driving terms are added on top of it
+// at the times each integrator needs them.
+pub fn iter_dt<T: Real>(
+    psi0: &DVector<Complex<T>>,
+    potential: &DVector<Complex<T>>,
+    t: f64,
+    s: &Scenario,
+) -> DVector<Complex<T>> {
+    match s.time.integrator {
+        Integrator::RungeKutta4 => rk4_iter_dt(psi0, potential, t, s),
+        Integrator::CrankNicolson => cn_iter_dt(psi0, potential, t, s),
+        Integrator::SplitOperator => split_operator_iter_dt(psi0, potential, t, s),
+    }
+}
 
-    psi0 + Complex::from_real(1. / 6.)
-        * &(k1 + Complex::from_real(2.) * &k2 + Complex::from_real(2.) * &k3 + &k4)
+// iter_dt in place, where RK4 keeps its buffers in the workspace instead of allocating them.
+// This is what the long running loops use.
+pub fn iter_dt_in_place<T: Real>(
+    psi: &mut DVector<Complex<T>>,
+    potential: &DVector<Complex<T>>,
+    t: f64,
+    s: &Scenario,
+    workspace: &mut Rk4Workspace<T>,
+) {
+    match s.time.integrator {
+        Integrator::RungeKutta4 => rk4_step(psi, potential, t, s, workspace),
+        _ => *psi = iter_dt(psi, potential, t, s),
+    }
+}
+
+// the stages of RK4 are taken at t, t + dt/2 (twice) and t + dt
+pub fn rk4_iter_dt<T: Real>(
+    psi0: &DVector<Complex<T>>,
+    potential: &DVector<Complex<T>>,
+    t: f64,
+    s: &Scenario,
+) -> DVector<Complex<T>> {
+    let mut psi = psi0.clone();
+    rk4_step(&mut psi, potential, t, s, &mut Rk4Workspace::default());
+    psi
+}
+
+// rk4_iter_dt in place, with the driving terms added at every point as it is reached
+pub fn rk4_step<T: Real>(
+    psi: &mut DVector<Complex<T>>,
+    potential: &DVector<Complex<T>>,
+    t: f64,
+    s: &Scenario,
+    workspace: &mut Rk4Workspace<T>,
+) {
+    let stencil = Stencil::new(potential, s.is_driven(), s);
+    let factor = Complex::new(T::zero(), T::of(-s.time.dt / s.constants.h_bar()));
+    workspace.step(psi.as_mut_slice(), t, s.time.dt, factor, |f, j, t| {
+        stencil.apply(f, j, t)
+    });
+}
+
+// Applies the finite difference Hamiltonian H = -hbar^2/2m * d^2/dx^2 + V to f.
+pub fn hamiltonian<T: Real>(
+    f: &DVector<Complex<T>>,
+    potential: &DVector<Complex<T>>,
+    s: &Scenario,
+) -> DVector<Complex<T>> {
+    let stencil = Stencil::new(potential, false, s);
+    let mut res = DVector::from_element(f.len(), Complex::zero());
+    for_each_chunk(res.as_mut_slice(), |start, chunk| {
+        for (offset, value) in chunk.iter_mut().enumerate() {
+            *value = stencil.apply(f.as_slice(), start + offset, 0.);
+        }
+    });
+    res
+}
+
+// H at a single point, i.e. the three point stencil for d^2/dx^2 together with the potential,
+// so that it can be applied to any part of the grid without allocating
+pub struct Stencil<'a, T: Real> {
+    // -hbar^2/(2m*dx^2)
+    kinetic: T,
+    // None without any potential, in which case there is no reason to look it up either
+    potential: Option<&'a [Complex<T>]>,
+    // whether the driving terms still have to be added to the potential
+    driven: bool,
+    s: &'a Scenario,
 }
+impl<'a, T: Real> Stencil<'a, T> {
+    pub fn new(potential: &'a DVector<Complex<T>>, driven: bool, s: &'a Scenario) -> Self {
+        let (h_bar, m, dx) = (s.constants.h_bar(), s.constants.m, s.grid.spacing);
+        Self {
+            kinetic: T::of(-(h_bar.powi(2) / (2. * m)) / dx.powi(2)),
+            potential: s.has_potential().then_some(potential.as_slice()),
+            driven,
+            s,
+        }
+    }
 
-pub fn d_dt(f: &DVector<Complex>) -> DVector<Complex> {
-    let f_h = -(H_BAR.powi(2) / (2. * M)) * Complex::from_real(1. / DX.powi(2)) * f;
+    // (H f)_j at time t
+    pub fn apply(&self, f: &[Complex<T>], j: usize, t: f64) -> Complex<T> {
+        let periodic = self.s.boundary.periodic;
+        let last = f.len() - 1;
+        // zero outside of the grid, or the other end with periodic boundaries, see outside
+        let before = if j > 0 {
+            f[j - 1]
+        } else if periodic {
+            f[last]
+        } else {
+            Complex::zero()
+        };
+        let after = if j < last {
+            f[j + 1]
+        } else if periodic {
+            f[0]
+        } else {
+            Complex::zero()
+        };
+        let mut res = (before - f[j] * T::of(2.) + after) * self.kinetic;
+        if let Some(potential) = self.potential {
+            let mut v = potential[j];
+            if self.driven {
+                v += T::of(self.s.potential.value_at(self.s.grid.point(j), t));
+            }
+            res += v * f[j];
+        }
+        res
+    }
+}
+
+// d^2f/dx^2 from the three point stencil (f[i-1] - 2f[i] + f[i+1]) / dx^2
+pub fn second_derivative<T: Real>(f: &DVector<Complex<T>>, s: &Scenario) -> DVector<Complex<T>> {
+    let factor = T::of(1. / s.grid.spacing.powi(2));
+    let two = T::of(2.);
     let last = f.len() - 1;
-    let mut pre_deriv = vec![-2. * f_h[0] + f_h[1]];
+    let (before_first, after_last) = outside(f, s);
+    let mut pre_deriv = vec![(before_first - f[0] * two + f[1]) * factor];
     for i in 1..last {
-        pre_deriv.push(f_h[i - 1] - 2. * f_h[i] + f_h[i + 1]);
+        pre_deriv.push((f[i - 1] - f[i] * two + f[i + 1]) * factor);
     }
-    pre_deriv.push(f_h[last - 1] + -2. * f_h[last]);
-    let deriv = DVector::from(pre_deriv);
-
-    if POTENTIAL {
-        let potent = &mut DVector::from(
-            ((-L / (2. * DX)) as isize..=(L / (2. * DX)) as isize)
-                .map(|x| v(x as f64 * DX))
-                .collect::<Vec<Complex>>(),
-        );
-        for i in 0..f.len() {
-            potent[i] *= f[i];
-        }
-        (DT / Complex::new(0., H_BAR)) * (deriv + potent.to_owned())
+    pre_deriv.push((f[last - 1] - f[last] * two + after_last) * factor);
+    DVector::from(pre_deriv)
+}
+
+// df/dx from the central difference (f[i+1] - f[i-1]) / 2dx
+pub fn first_derivative<T: Real>(f: &DVector<Complex<T>>, s: &Scenario) -> DVector<Complex<T>> {
+    let factor = T::of(1. / (2. * s.grid.spacing));
+    let last = f.len() - 1;
+    let (before_first, after_last) = outside(f, s);
+    let mut pre_deriv = vec![(f[1] - before_first) * factor];
+    for i in 1..last {
+        pre_deriv.push((f[i + 1] - f[i - 1]) * factor);
+    }
+    pre_deriv.push((after_last - f[last - 1]) * factor);
+    DVector::from(pre_deriv)
+}
+
+// The values just outside of either end of the grid. They are taken to be zero (hard walls),
+// unless the boundary is periodic, in which case the two ends of the grid are neighbours.
+fn outside<T: Real>(f: &DVector<Complex<T>>, s: &Scenario) -> (Complex<T>, Complex<T>) {
+    if s.boundary.periodic {
+        (f[f.len() - 1], f[0])
+    } else {
+        (Complex::zero(), Complex::zero())
+    }
+}
+
+// The potential sampled at every point of the grid, which is what all of the propagators use
+pub fn potential_vector(s: &Scenario) -> DVector<Complex> {
+    DVector::from(
+        s.grid
+            .points()
+            .iter()
+            .map(|x| v(*x, s))
+            .collect::<Vec<Complex>>(),
+    )
+}
+
+// The sampled potential together with the driving terms at time t.
+// Without any driving terms this is just the sampled potential itself.
+pub fn potential_at<'a, T: Real>(
+    potential: &'a DVector<Complex<T>>,
+    t: f64,
+    s: &Scenario,
+) -> Cow<'a, DVector<Complex<T>>> {
+    if !s.is_driven() {
+        return Cow::Borrowed(potential);
+    }
+    let points = s.grid.points();
+    Cow::Owned(DVector::from_fn(potential.len(), |j, _| {
+        potential[j] + T::of(s.potential.value_at(points[j], t))
+    }))
+}
+
+// Crank-Nicolson takes the average of the explicit and implicit Euler steps, i.e.
+// (1 + i*dt/(2*hbar) * H) psi(t + dt) = (1 - i*dt/(2*hbar) * H) psi(t).
+// The operator on the left is a Cayley transform of H, which makes the step unitary
+// and stable for any dt. Since H is tridiagonal (with corners for periodic boundaries),
+// the system is solved in O(n). A time dependent H is taken at the middle of the step,
+// which keeps the scheme second order.
+pub fn cn_iter_dt<T: Real>(
+    psi0: &DVector<Complex<T>>,
+    potential: &DVector<Complex<T>>,
+    t: f64,
+    s: &Scenario,
+) -> DVector<Complex<T>> {
+    let potential = &*potential_at(potential, t + s.time.dt / 2., s);
+    let (h_bar, m, dx) = (s.constants.h_bar(), s.constants.m, s.grid.spacing);
+    let alpha = Complex::new(T::zero(), T::of(s.time.dt / (2. * h_bar)));
+    let rhs = psi0 - alpha * hamiltonian(psi0, potential, s);
+
+    // H has -hbar^2/(2m*dx^2) on the off diagonals and hbar^2/(m*dx^2) + V on the diagonal
+    let off = T::of(-(h_bar.powi(2) / (2. * m * dx.powi(2))));
+    let diag = if s.has_potential() {
+        potential.clone()
     } else {
-        // if there is no potential at all, there is no reason to
-        // calculate the potential vector either
-        (DT / Complex::new(0., H_BAR)) * deriv
+        DVector::from(vec![Complex::zero(); psi0.len()])
     }
+    .map(|v| Complex::one() + alpha * (v - T::of(2.) * off));
+    let sub = vec![alpha * off; psi0.len() - 1];
+
+    DVector::from(if s.boundary.periodic {
+        let corner = alpha * off;
+        solve_cyclic_tridiagonal(&sub, diag.as_slice(), &sub, corner, corner, rhs.as_slice())
+    } else {
+        solve_tridiagonal(&sub, diag.as_slice(), &sub, rhs.as_slice())
+    })
+}
+
+// Strang splitting of e^(-i*H*dt/hbar) into e^(-i*V*dt/(2*hbar)) e^(-i*T*dt/hbar) e^(-i*V*dt/(2*hbar)).
+// The potential is diagonal in position space and the kinetic energy hbar^2*k^2/2m is diagonal
+// in momentum space, so each factor is a pointwise multiplication with an fft in between.
+// Note that the fft makes the grid periodic, so a packet leaving at one end reappears at the other,
+// no matter which boundary the scenario asks for.
+// Driving terms are taken at the middle of the step, where both potential factors are applied.
+pub fn split_operator_iter_dt<T: Real>(
+    psi0: &DVector<Complex<T>>,
+    potential: &DVector<Complex<T>>,
+    t: f64,
+    s: &Scenario,
+) -> DVector<Complex<T>> {
+    let potential = &*potential_at(potential, t + s.time.dt / 2., s);
+    let (h_bar, m, dt) = (s.constants.h_bar(), s.constants.m, s.time.dt);
+    let n = psi0.len();
+
+    let half_potential = s.has_potential().then(|| {
+        potential.map(|v| Complex::exp(Complex::new(T::zero(), T::of(-dt / (2. * h_bar))) * v))
+    });
+
+    let mut psi = psi0.clone();
+    if let Some(half_potential) = &half_potential {
+        psi.component_mul_assign(half_potential);
+    }
+
+    let kinetic = DVector::from(
+        fft_frequencies(n, s.grid.spacing)
+            .iter()
+            .map(|k| Complex::exp(i() * (-h_bar * k.powi(2) * dt / (2. * m))).cast())
+            .collect::<Vec<Complex<T>>>(),
+    );
+    psi = ifft(&fft(&psi).component_mul(&kinetic));
+
+    if let Some(half_potential) = &half_potential {
+        psi.component_mul_assign(half_potential);
+    }
+    psi
 }
 
 // Using matrix multiplication is a lot more elegant, and easier to get right.
@@ -48,9 +283,10 @@ pub fn d_dt(f: &DVector<Complex>) -> DVector<Complex> {
 // This is because when increasing L, the traditional calculation grows linearly O(n)
 // while the matrix multiplication grows quadratically O(n^2).
 //
-// The matrix functions are kept because they serve as a nice test for the faster rk4_iter_dt
-// function
-#[allow(dead_code)]
+// rk4_matrix_mul is kept because it serves as a nice test for the faster rk4_iter_dt function.
+// The dense Hamiltonian from descrete_derivative_matrix is also what the eigensolver
+// diagonalizes for periodic grids, see eigen.rs.
+#[cfg(test)]
 pub fn rk4_matrix_mul(psi0: &DVector<Complex>, U: &DMatrix<Complex>) -> DVector<Complex> {
     let k1 = U * psi0;
     let k2 = U * (psi0 + Complex::from_real(0.5) * &k1);
@@ -61,9 +297,8 @@ pub fn rk4_matrix_mul(psi0: &DVector<Complex>, U: &DMatrix<Complex>) -> DVector<
         * &(k1 + Complex::from_real(2.) * &k2 + Complex::from_real(2.) * &k3 + &k4)
 }
 
-// helper functions for rk4_matrix_mul
-#[allow(dead_code)]
-pub fn descrete_derivative_matrix(size: usize) -> DMatrix<Complex> {
+// the kinetic part of the dense Hamiltonian, with the corners filled in for periodic grids
+pub fn descrete_derivative_matrix(size: usize, s: &Scenario) -> DMatrix<Complex> {
     let mut m = DMatrix::from_diagonal(&DVector::from(vec![(-2.).into(); size]));
 
     let mut ones = Vec::new();
@@ -83,15 +318,23 @@ pub fn descrete_derivative_matrix(size: usize) -> DMatrix<Complex> {
     }
 
     m += ones_matrix.clone() + ones_matrix.transpose();
+    if s.boundary.periodic {
+        m[(0, size - 1)] = Complex::one();
+        m[(size - 1, 0)] = Complex::one();
+    }
 
-    -(H_BAR.powi(2) / (2. * M)) * Complex::from_real(1. / DX.powi(2)) * m
+    -(s.constants.h_bar().powi(2) / (2. * s.constants.m))
+        * Complex::from_real(1. / s.grid.spacing.powi(2))
+        * m
 }
 
-#[allow(dead_code)]
-pub fn descrete_potential_matrix(v: Box<dyn Fn(f64) -> Complex>) -> DMatrix<Complex> {
+#[cfg(test)]
+pub fn descrete_potential_matrix(v: &dyn Fn(f64) -> Complex, s: &Scenario) -> DMatrix<Complex> {
     DMatrix::from_diagonal(&DVector::from(
-        ((-L / (2. * DX)) as isize..=(L / (2. * DX)) as isize)
-            .map(|x| v(x as f64 * DX))
+        s.grid
+            .points()
+            .iter()
+            .map(|x| v(*x))
             .collect::<Vec<Complex>>(),
     ))
 }
@@ -1,92 +1,106 @@
-use crate::consts::*;
-const DX: f64 = 0.01;
-const DT: f64 = 0.0005;
-
-// simulation specifics
-const POTENTIAL: bool = false;
-
 // External crates
 use nalgebra::DVector;
 use num_traits::Zero;
-use std::f64::consts::{E, PI};
+use std::{f64::consts::PI, io::Error};
 
 // internal modules
-use crate::utils::simpsons_rule;
-mod iteration;
+use crate::fft::{fft, fft_frequencies};
+use crate::initial::WaveBuilder;
+use crate::potential::Potential;
+use crate::quadrature::simpson;
+pub mod eigen;
+mod headless;
+mod imaginary_time;
+pub mod iteration;
+mod observables;
+mod scattering;
+#[cfg(feature = "visual")]
 mod visuals;
 use crate::complex::{Complex, *};
+use crate::scenario::Scenario;
+use crate::Config;
 #[cfg(test)]
 mod test;
+#[cfg(test)]
+mod validation;
 
-pub fn run(visual: bool) {
-    if visual {
-        visuals::oneD();
+pub fn run(cfg: &Config) -> Result<(), Error> {
+    #[cfg(feature = "visual")]
+    if cfg.vis() {
+        // fails on an initial state that can't be built before the window opens
+        wave(cfg.scenario())?;
+        visuals::oneD(
+            cfg.scenario().clone(),
+            cfg.visual_exporter(),
+            cfg.visual_checkpoint(),
+            cfg.resume().cloned(),
+        );
+        return Ok(());
     }
+    headless::run(cfg)
 }
 
-fn barriers() -> Vec<Box<dyn Fn(f64) -> Complex>> {
-    vec![
-        // rectangle shaped barrier between x = 2.5 and x = 3
-        Box::new(|x| {
-            if x > 2.5 && x < 3. {
-                Complex::from_real(1.)
-            } else {
-                Complex::zero()
-            }
-        }),
-        // quadratic potential based around zero
-        Box::new(|x| Complex::from_real(x.powi(2))),
-    ]
+// an eigenstate of the Hamiltonian together with its energy
+#[derive(Debug, Clone)]
+pub struct StationaryState {
+    pub energy: f64,
+    pub psi: DVector<Complex>,
 }
-fn v(x: f64) -> Complex {
-    let mut res = Complex::zero();
-    for b in barriers() {
-        res += b(x);
+
+// <a|b> = int{a*(x) b(x)}dx, taken as a plain sum since the finite difference Hamiltonian
+// is symmetric (and its eigenvectors orthogonal) with respect to exactly that sum
+fn inner_product(a: &DVector<Complex>, b: &DVector<Complex>, s: &Scenario) -> Complex {
+    let mut sum = Complex::zero();
+    for (a, b) in a.iter().zip(b.iter()) {
+        sum += a.complex_conjugate() * *b;
     }
-    res
+    sum * s.grid.spacing
 }
 
-// Creates a wave vector (vector containing the wave function's value at equally spaced
-// x values) by assuming psi = int{c(k)e^(ikx)}dk, where c_k is a gaussian.
-fn wave() -> (DVector<f64>, DVector<Complex>) {
-    // the central value of c(k)
-    let k_0: isize = 10;
-    // we cannot integrate form -infty..infty, thus we make the cut-off at this value
-    let k_range: isize = 10; // 10
-    let dk = 0.5; // discretesation of the grid of k
-    let k_values = ((k_0 - k_range)..=(k_0 + k_range))
-        .map(|x| x as f64 * dk)
-        .collect::<Vec<f64>>();
-
-    let delta_k = 5f64; // width of the gaussian
-
-    // c(k) = e^(-(k-k_0)/dk)^2
-    let c_k = |k: f64| E.powf(-((k - k_0 as f64) / delta_k).powi(2));
-    // psi_n(x, k) = e^(ikx)
-    let f_k = |x: f64, k: f64| Complex::exp(i() * (k * x));
-
-    let x_values: Vec<f64> = ((-L / (2. * DX)) as isize..=(L / (2. * DX)) as isize)
-        .map(|x| x as f64 * DX)
-        .collect();
+// Sum of all of the terms of the potential described by the scenario,
+// including the imaginary part from an absorbing boundary
+fn v(x: f64, s: &Scenario) -> Complex {
+    let mut res = Complex::from_real(s.potential.value(x));
+    if let Some(layer) = &s.boundary.absorbing {
+        res -= i() * layer.value(x, s.grid.length);
+    }
+    res
+}
 
-    let mut wave: Vec<Complex> = Vec::new();
-    for x in x_values.clone() {
-        let mut p_x = Complex::zero();
-        for k in &k_values {
-            p_x += c_k(*k) * f_k(x, *k);
-        }
+// The initial wave function described by the scenario, sampled at the grid points.
+fn wave(s: &Scenario) -> Result<(DVector<f64>, DVector<Complex>), Error> {
+    Ok((
+        DVector::from(s.grid.points()),
+        WaveBuilder::from_scenario(s).build_1d(s)?,
+    ))
+}
 
-        wave.push(p_x);
-    }
+// The wave function in momentum space, phi(k) = 1/sqrt(2*pi) int{psi(x)e^(-ikx)}dx, sampled at
+// the wave numbers of the fft and sorted from the lowest to the highest k. Uses the same
+// normalization as psi, i.e. int{|phi(k)|^2}dk = int{|psi(x)|^2}dx.
+fn momentum_space(psi: &DVector<Complex>, s: &Scenario) -> (DVector<f64>, DVector<Complex>) {
+    let dx = s.grid.spacing;
+    let x_0 = -((s.grid.length / (2. * dx)) as isize as f64) * dx;
+    let mut phi = fft_frequencies(psi.len(), dx)
+        .into_iter()
+        .zip(fft(psi).iter())
+        // the fft starts counting at x = 0 rather than at the first grid point
+        .map(|(k, p)| {
+            let shift = Complex::exp(i() * (-k * x_0));
+            (k, dx / (2. * PI).sqrt() * shift * *p)
+        })
+        .collect::<Vec<(f64, Complex)>>();
+    phi.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
 
-    (DVector::from(x_values), DVector::from(normalize(wave)))
+    let (k, phi): (Vec<f64>, Vec<Complex>) = phi.into_iter().unzip();
+    (DVector::from(k), DVector::from(phi))
 }
 
 // Assuming equally spaced points, using simpsons rule makes it so the square of the
 // inputted data itegrates to 1.
-fn normalize(data: Vec<Complex>) -> Vec<Complex> {
-    let data_squared = data.clone().iter().map(|x| x.abs_squared()).collect();
-    let tot_integral = simpsons_rule(data_squared, -L / 2., L / 2.);
+fn normalize(data: Vec<Complex>, s: &Scenario) -> Vec<Complex> {
+    let data_squared = data.iter().map(|x| x.abs_squared()).collect::<Vec<f64>>();
+    let tot_integral = simpson(&data_squared, s.grid.spacing);
     // Each value must be devided by the root of the total integral since
     // we're considering the squares of each data point.
     data.iter().map(|x| *x / tot_integral.sqrt()).collect()
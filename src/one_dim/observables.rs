@@ -0,0 +1,85 @@
+use nalgebra::DVector;
+
+use super::iteration::{first_derivative, second_derivative};
+use crate::{complex::Complex, quadrature::simpson, scenario::Scenario};
+
+// Expectation values of a wave function on the 1D grid. All of them are divided by the
+// norm, so they are correct even if the wave function is not normalized (or is losing
+// probability to an absorbing boundary).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Observables {
+    // int{|psi|^2}dx
+    pub norm: f64,
+    pub x: f64,
+    pub x_squared: f64,
+    pub p: f64,
+    pub p_squared: f64,
+    // <p^2>/2m
+    pub kinetic: f64,
+    // <V>, using the real part of the potential
+    pub potential: f64,
+}
+impl Observables {
+    // potential is the sampled potential from potential_vector
+    pub fn compute(psi: &DVector<Complex>, potential: &DVector<Complex>, s: &Scenario) -> Self {
+        let h_bar = s.constants.h_bar();
+        let x = s.grid.points();
+        let prob = psi.iter().map(|p| p.abs_squared()).collect::<Vec<f64>>();
+
+        let norm = integrate(prob.iter().copied(), s);
+        let x_mean = integrate(x.iter().zip(&prob).map(|(x, p)| x * p), s) / norm;
+        let x_squared = integrate(x.iter().zip(&prob).map(|(x, p)| x.powi(2) * p), s) / norm;
+
+        // p = -i*hbar*d/dx and p^2 = -hbar^2*d^2/dx^2
+        let d_psi = first_derivative(psi, s);
+        let d2_psi = second_derivative(psi, s);
+        let p = integrate(
+            psi.iter()
+                .zip(d_psi.iter())
+                .map(|(p, d_p)| (p.complex_conjugate() * *d_p).imag() * h_bar),
+            s,
+        ) / norm;
+        let p_squared = integrate(
+            psi.iter()
+                .zip(d2_psi.iter())
+                .map(|(p, d2_p)| -(p.complex_conjugate() * *d2_p).real() * h_bar.powi(2)),
+            s,
+        ) / norm;
+
+        let potential = if s.has_potential() {
+            integrate(potential.iter().zip(&prob).map(|(v, p)| v.real() * p), s) / norm
+        } else {
+            0.
+        };
+
+        Self {
+            norm,
+            x: x_mean,
+            x_squared,
+            p,
+            p_squared,
+            kinetic: p_squared / (2. * s.constants.m),
+            potential,
+        }
+    }
+
+    // <H>
+    pub fn energy(&self) -> f64 {
+        self.kinetic + self.potential
+    }
+
+    pub fn delta_x(&self) -> f64 {
+        (self.x_squared - self.x.powi(2)).max(0.).sqrt()
+    }
+    pub fn delta_p(&self) -> f64 {
+        (self.p_squared - self.p.powi(2)).max(0.).sqrt()
+    }
+    // should never be smaller than hbar/2
+    pub fn uncertainty(&self) -> f64 {
+        self.delta_x() * self.delta_p()
+    }
+}
+
+fn integrate(values: impl Iterator<Item = f64>, s: &Scenario) -> f64 {
+    simpson(&values.collect::<Vec<f64>>(), s.grid.spacing)
+}
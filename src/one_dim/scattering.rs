@@ -0,0 +1,235 @@
+use std::io::{Error, ErrorKind};
+
+use nalgebra::DVector;
+use num_traits::Zero;
+
+use super::{iteration::iter_dt_in_place, momentum_space, observables::Observables};
+use crate::{complex::*, scenario::Scenario, utils::Rk4Workspace};
+
+// The part of the grid where the potential is not constant, [start, end) in grid indices.
+// Outside of it the potential takes the values at the left and right edge of the grid, so a
+// step has an empty region at the index where it jumps.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Region {
+    pub start: usize,
+    pub end: usize,
+}
+impl Region {
+    // uses the real part of the sampled potential, so an absorbing layer doesn't count
+    pub fn find(potential: &DVector<Complex>) -> Result<Self, Error> {
+        let n = potential.len();
+        let (left, right) = (potential[0].real(), potential[n - 1].real());
+        let start = (0..n).find(|j| potential[*j].real() != left);
+        let end = (0..n).rev().find(|j| potential[*j].real() != right);
+        let (Some(start), Some(end)) = (start, end) else {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "The potential is constant, so there is nothing to scatter off",
+            ));
+        };
+        if start <= 1 || end >= n - 2 {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "The potential has to level off before the edges of the grid",
+            ));
+        }
+        Ok(Self {
+            start,
+            end: end + 1,
+        })
+    }
+}
+
+// How a packet was split up by the potential, as fractions of its initial norm
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Scattering {
+    // on the side the packet came from, including what was absorbed on that side
+    pub reflected: f64,
+    // on the other side, including what was absorbed there
+    pub transmitted: f64,
+    // still close to the potential when the run stopped
+    pub remaining: f64,
+    pub time: f64,
+}
+impl Scattering {
+    pub fn sum(&self) -> f64 {
+        self.reflected + self.transmitted
+    }
+}
+
+// Runs psi into the potential until the probability close to the region drops below tolerance,
+// after having been above it, and then integrates |psi|^2 on either side. "Close" is within one
+// initial packet width of the region, where the packet has to start outside of. With an
+// absorbing boundary, whatever is absorbed at each edge is added to that side, which keeps
+// packets from coming back off the edges of the grid.
+pub fn scatter(
+    psi0: &DVector<Complex>,
+    potential: &DVector<Complex>,
+    s: &Scenario,
+    tolerance: f64,
+    max_steps: usize,
+) -> Result<Scattering, Error> {
+    let region = Region::find(potential)?;
+    let x = s.grid.points();
+    let dx = s.grid.spacing;
+    let initial = Observables::compute(psi0, potential, s);
+    let width = initial.delta_x();
+    let (near_start, near_end) = (x[region.start] - width, x[region.end - 1] + width);
+    if initial.x > near_start && initial.x < near_end {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            "The packet has to start away from the potential",
+        ));
+    }
+    let from_left = initial.x <= near_start;
+
+    let norm = |psi: &DVector<Complex>, f: &dyn Fn(f64) -> bool| {
+        psi.iter()
+            .zip(&x)
+            .filter(|(_, x)| f(**x))
+            .map(|(p, _)| p.abs_squared())
+            .sum::<f64>()
+            * dx
+    };
+    let initial_norm = norm(psi0, &|_| true);
+    let near = |x: f64| x >= near_start && x <= near_end;
+    let left = |x: f64| x < near_start;
+    let right = |x: f64| x > near_end;
+
+    // the probability absorbed per step is 2*dt/hbar * int{W|psi|^2}dx, with -W the imaginary
+    // part of the potential
+    let absorbed = |psi: &DVector<Complex>, f: &dyn Fn(f64) -> bool| {
+        psi.iter()
+            .zip(potential.iter())
+            .zip(&x)
+            .filter(|(_, x)| f(**x))
+            .map(|((p, v), _)| -v.imag() * p.abs_squared())
+            .sum::<f64>()
+            * dx
+            * 2.
+            * s.time.dt
+            / s.constants.h_bar()
+    };
+    let (mut absorbed_left, mut absorbed_right) = (0., 0.);
+
+    let mut psi = psi0.clone();
+    let mut workspace = Rk4Workspace::default();
+    let mut arrived = false;
+    for step in 0..max_steps {
+        let remaining = norm(&psi, &near) / initial_norm;
+        arrived |= remaining >= tolerance;
+        if arrived && remaining < tolerance {
+            let left = norm(&psi, &left) + absorbed_left;
+            let right = norm(&psi, &right) + absorbed_right;
+            let (reflected, transmitted) = if from_left {
+                (left, right)
+            } else {
+                (right, left)
+            };
+            return Ok(Scattering {
+                reflected: reflected / initial_norm,
+                transmitted: transmitted / initial_norm,
+                remaining,
+                time: step as f64 * s.time.dt,
+            });
+        }
+        if s.boundary.absorbing.is_some() {
+            absorbed_left += absorbed(&psi, &left);
+            absorbed_right += absorbed(&psi, &right);
+        }
+        iter_dt_in_place(
+            &mut psi,
+            potential,
+            step as f64 * s.time.dt,
+            s,
+            &mut workspace,
+        );
+    }
+    Err(Error::other(format!(
+        "The packet {} within {max_steps} steps",
+        if arrived {
+            "didn't leave the potential"
+        } else {
+            "never reached the potential"
+        }
+    )))
+}
+
+// The transmission coefficient of a plane wave with the given energy, coming from the left.
+// Every grid point in the region is taken as a cell of constant potential, and psi and psi'
+// are matched at the cell boundaries with a transfer matrix. In each cell
+// psi = A e^(ik(x-x_l)) + B e^(-ik(x-x_l)) with x_l its left boundary and hbar*k = sqrt(2m(E-V)),
+// which is imaginary for E < V. Starting from A = t, B = 0 on the right, and going
+// to the left, gives 1/t as the incoming amplitude.
+pub fn transmission(energy: f64, potential: &DVector<Complex>, s: &Scenario) -> Result<f64, Error> {
+    let region = Region::find(potential)?;
+    let n = potential.len();
+    let (v_left, v_right) = (potential[0].real(), potential[n - 1].real());
+    if energy <= v_left || energy <= v_right {
+        return Ok(0.);
+    }
+
+    let h_bar = s.constants.h_bar();
+    let k = |v: f64| {
+        let k = Complex::from_real(2. * s.constants.m * (energy - v) / h_bar.powi(2)).sqrt();
+        // avoids dividing by zero when E is exactly at the top of a cell
+        if k.abs() < 1e-12 {
+            Complex::from_real(1e-12)
+        } else {
+            k
+        }
+    };
+
+    // (A, B) to the left of a boundary from (A, B) to the right of it, both taken at the
+    // boundary itself, since psi and k*(A-B) are continuous there
+    let matched = |(a, b): (Complex, Complex), k_left: Complex, k_right: Complex| {
+        let ratio = k_right / k_left;
+        (
+            0.5 * ((1. + ratio) * a + (1. - ratio) * b),
+            0.5 * ((1. - ratio) * a + (1. + ratio) * b),
+        )
+    };
+
+    // t = 1 on the right, after which every cell is dx wide
+    let mut amplitudes = (Complex::from_real(1.), Complex::zero());
+    let mut k_right = k(v_right);
+    for j in (region.start..region.end).rev() {
+        let k_cell = k(potential[j].real());
+        let (a, b) = matched(amplitudes, k_cell, k_right);
+        let phase = Complex::exp(i() * (k_cell * s.grid.spacing));
+        amplitudes = (a / phase, b * phase);
+        k_right = k_cell;
+    }
+    // the left region has its origin at the first boundary
+    let (a, _) = matched(amplitudes, k(v_left), k_right);
+    Ok((k(v_right) / k(v_left)).real() / a.abs_squared())
+}
+
+// T(E) averaged over the momentum distribution of psi, counting only what moves towards the
+// potential, which is what the packet should see on average. T(E) is the same from either side.
+pub fn packet_transmission(
+    psi: &DVector<Complex>,
+    potential: &DVector<Complex>,
+    s: &Scenario,
+) -> Result<f64, Error> {
+    let region = Region::find(potential)?;
+    let x = Observables::compute(psi, potential, s).x;
+    let from_left = x < s.grid.points()[region.start];
+    let v = if from_left {
+        potential[0].real()
+    } else {
+        potential[potential.len() - 1].real()
+    };
+
+    let (k, phi) = momentum_space(psi, s);
+    let mut weight = 0.;
+    let mut transmitted = 0.;
+    for (k, phi) in k.iter().zip(phi.iter()) {
+        weight += phi.abs_squared();
+        if (*k > 0.) == from_left {
+            let energy = (s.constants.h_bar() * k).powi(2) / (2. * s.constants.m) + v;
+            transmitted += phi.abs_squared() * transmission(energy, potential, s)?;
+        }
+    }
+    Ok(transmitted / weight)
+}
@@ -1,86 +1,537 @@
 use std::f64::consts::PI;
 
 use super::{
+    eigen::{expand, inverse_iteration, lowest_states},
+    imaginary_time::stationary_states,
+    inner_product,
     iteration::{
-        descrete_derivative_matrix, descrete_potential_matrix, rk4_iter_dt, rk4_matrix_mul,
+        cn_iter_dt, descrete_derivative_matrix, descrete_potential_matrix, hamiltonian, iter_dt,
+        iter_dt_in_place, potential_at, potential_vector, rk4_iter_dt, rk4_matrix_mul,
+        split_operator_iter_dt,
     },
-    v, wave, DT, H_BAR,
+    momentum_space,
+    observables::Observables,
+    scattering::{packet_transmission, scatter, transmission},
+    v, wave,
 };
-use crate::complex::*;
+use crate::{
+    checkpoint::Checkpoint,
+    complex::*,
+    initial::WaveBuilder,
+    potential::{Barrier, ElectricField, Envelope, Harmonic, Kick, Step},
+    scenario::{AbsorbingLayer, Builtin, Component, DrivingTerm, Integrator, Scenario, Shape},
+    test::rk4_reference,
+    utils::Rk4Workspace,
+    Simulation,
+};
+use nalgebra::DVector;
+use num_traits::Zero;
+
+#[test]
+#[allow(non_snake_case)]
+fn oneD_iter() {
+    // the potential is always part of the matrix, so it has to be enabled for the vector as well
+    let mut s = Scenario::default();
+    s.potential.enabled = true;
+    let wave0 = wave(&s).unwrap();
+
+    // iteration with matrix multiplication is reliable but horribly slow
+    // Serves as a good test reference for the faster vector iteration rk4 method
+    // that is trickier to get right.
+    let size = wave0.0.len();
+    let T = descrete_derivative_matrix(size, &s);
+
+    let V = descrete_potential_matrix(&|x| v(x, &s), &s);
+
+    let U = (s.time.dt / Complex::new(0., s.constants.h_bar())) * (&T + &V);
+    let iter_matrix = rk4_matrix_mul(&wave0.1, &U);
+
+    let iter_vector = rk4_iter_dt(&wave0.1, &potential_vector(&s), 0., &s);
+
+    for i in 0..size {
+        // the resulting values should be equal (with some leeway for floating point errors)
+        assert!((iter_matrix[i] - iter_vector[i]).abs() < 1e-12)
+    }
+}
+
+#[test]
+fn crank_nicolson_norm() {
+    let s = Scenario::default();
+    let wave0 = wave(&s).unwrap();
+    let norm = |psi: &DVector<Complex>| psi.iter().map(|x| x.abs_squared()).sum::<f64>();
+    let norm0 = norm(&wave0.1);
+
+    // the Crank-Nicolson step is unitary, so the norm should only drift by rounding errors
+    let potential = potential_vector(&s);
+    let mut psi = wave0.1;
+    for _ in 0..1000 {
+        psi = cn_iter_dt(&psi, &potential, 0., &s);
+    }
+    assert!((norm(&psi) - norm0).abs() / norm0 < 1e-12);
+}
+
+#[test]
+fn split_operator_norm() {
+    let s = Scenario::default();
+    let wave0 = wave(&s).unwrap();
+    let norm = |psi: &DVector<Complex>| psi.iter().map(|x| x.abs_squared()).sum::<f64>();
+    let norm0 = norm(&wave0.1);
+
+    let potential = potential_vector(&s);
+    let mut psi = wave0.1;
+    for _ in 0..200 {
+        psi = split_operator_iter_dt(&psi, &potential, 0., &s);
+    }
+    assert!((norm(&psi) - norm0).abs() / norm0 < 1e-12);
+}
+
+#[test]
+fn imaginary_time_harmonic_oscillator() {
+    // V = 8x^2 = m*w^2*x^2/2 with w = 4, so E_n = hbar*w*(n + 1/2)
+    let mut s = Scenario::default();
+    s.grid.spacing = 0.02;
+    s.time.dt = 0.001;
+    s.potential.enabled = true;
+    s.potential.terms = vec![Builtin::Harmonic(Harmonic { strength: 8. }).into()];
+    let h_bar_w = s.constants.h_bar() * 4.;
+
+    let states = stationary_states(3, &s, 1e-10, 100_000).unwrap();
+    for (n, state) in states.iter().enumerate() {
+        let exact = h_bar_w * (n as f64 + 0.5);
+        assert!((state.energy - exact).abs() / exact < 5e-3);
+    }
+}
+
+#[test]
+#[allow(non_snake_case)]
+fn eigen_solver() {
+    let mut s = Scenario::default();
+    s.grid.spacing = 0.05;
+    s.potential.enabled = true;
+    let size = s.grid.points().len();
+
+    // the dense Hamiltonian from the matrix helpers serves as a reference
+    let H = descrete_derivative_matrix(size, &s) + descrete_potential_matrix(&|x| v(x, &s), &s);
+    let reference = H.map(|x| x.real()).symmetric_eigen();
+    let mut energies = reference.eigenvalues.iter().copied().collect::<Vec<f64>>();
+    energies.sort_by(|a, b| a.partial_cmp(b).unwrap());
+
+    let states = lowest_states(5, &potential_vector(&s), &s);
+    for (state, energy) in states.iter().zip(energies) {
+        assert!((state.energy - energy).abs() < 1e-9);
+
+        // H psi = E psi
+        let residual = &H * &state.psi - Complex::from_real(state.energy) * &state.psi;
+        assert!(residual.iter().all(|x| x.abs_squared() < 1e-12));
+    }
+
+    // a state made up of eigenstates expands back into its coefficients
+    let psi = Complex::new(0.6, 0.) * &states[1].psi + Complex::new(0., 0.8) * &states[3].psi;
+    let coefficients = expand(&psi, &states, &s);
+    assert!((coefficients[1] - Complex::new(0.6, 0.)).abs_squared() < 1e-16);
+    assert!((coefficients[3] - Complex::new(0., 0.8)).abs_squared() < 1e-16);
+    assert!(coefficients[0].abs_squared() < 1e-16);
+
+    // shifting by an eigenvalue exactly makes the first pivot zero, 2 is the middle one of five
+    let psi = inverse_iteration(2., &[2.; 5], -1., &s);
+    assert!(psi.iter().all(|p| p.real().is_finite()));
+    let expected = [1., 0., -1., 0., 1.];
+    let overlap = psi
+        .iter()
+        .zip(expected)
+        .map(|(p, e)| *p * e)
+        .sum::<Complex>();
+    let norm = psi.iter().map(|p| p.abs_squared()).sum::<f64>() * 3.;
+    assert!((overlap.abs_squared() / norm - 1.).abs() < 1e-12);
+}
+
+#[test]
+fn absorbing_boundary() {
+    // dt is far too large for RK4
+    let mut s = Scenario::default();
+    s.time.integrator = Integrator::CrankNicolson;
+    s.time.dt = 0.005;
+    s.boundary.absorbing = Some(AbsorbingLayer {
+        width: 2.,
+        strength: 5.,
+    });
+    let norm = |psi: &DVector<Complex>| psi.iter().map(|x| x.abs_squared()).sum::<f64>();
+
+    // the packet moves to the right with <p> of about 1.3, so it has long reached the edge
+    let potential = potential_vector(&s);
+    let mut psi = wave(&s).unwrap().1;
+    let norm0 = norm(&psi);
+    for _ in 0..1200 {
+        psi = iter_dt(&psi, &potential, 0., &s);
+    }
+    assert!(norm(&psi) / norm0 < 0.02);
+}
 
 #[test]
-fn basic_complex_arithmetic() {
-    //addition (2-5i)+(-4+9i)
-    assert_eq!((2. - 5. * i()) + (-4. + 9. * i()), -2. + 4. * i());
-    assert_eq!((2. - 5. * i()) - (-4. + 9. * i()), 6. - 14. * i());
-    assert_eq!((2. - 5. * i()) * (-4. + 9. * i()), 37. + 38. * i());
-    assert_eq!(
-        (2. - 5. * i()) / (-4. + 9. * i()),
-        -53. / 97. + 2. / 97. * i()
+fn periodic_boundary() {
+    let mut s = Scenario::default();
+    s.boundary.periodic = true;
+
+    // a plane wave that fits onto the ring is an eigenstate of the periodic stencil,
+    // with E = hbar^2/(m*dx^2) * (1 - cos(2*pi*j/n)) for j periods around the ring
+    let n = s.grid.points().len();
+    let kinetic = s.constants.h_bar().powi(2) / (s.constants.m * s.grid.spacing.powi(2));
+    let energy = kinetic * (1. - (2. * PI * 3. / n as f64).cos());
+    let psi0 = DVector::from(
+        (0..n)
+            .map(|j| Complex::exp(i() * (2. * PI * 3. * j as f64 / n as f64)))
+            .collect::<Vec<Complex>>(),
     );
 
-    assert_eq!(i() * i(), Complex::new(-1., 0.));
+    // Crank-Nicolson multiplies each eigenstate by (1 - i*a*E)/(1 + i*a*E), a = dt/(2*hbar)
+    let a = s.time.dt / (2. * s.constants.h_bar());
+    let phase = (1. - i() * (a * energy)) / (1. + i() * (a * energy));
+    let psi1 = cn_iter_dt(&psi0, &potential_vector(&s), 0., &s);
+    for j in 0..n {
+        assert!((psi1[j] - phase * psi0[j]).abs_squared() < 1e-20);
+    }
+
+    // the same energies come out of the eigen solver
+    s.grid.spacing = 0.1;
+    let n = s.grid.points().len() as f64;
+    let kinetic = s.constants.h_bar().powi(2) / (s.constants.m * s.grid.spacing.powi(2));
+    let states = lowest_states(5, &potential_vector(&s), &s);
+    for (state, j) in states.iter().zip([0., 1., 1., 2., 2.]) {
+        let exact = kinetic * (1. - (2. * PI * j / n).cos());
+        assert!((state.energy - exact).abs() < 1e-10);
+    }
 }
 
 #[test]
-fn f64_complex_arithmetic() {
-    assert_eq!((2. - 5. * i()), Complex::new(2., -5.));
-    assert_eq!((-4. + 9. * i()), Complex::new(-4., 9.));
-    assert_eq!(3. * (1. - 2. * i()), Complex::new(3., -6.));
-    assert_eq!(1. / (4. + 6. * i()), Complex::new(1. / 13., -3. / 26.));
+fn observables() {
+    let mut s = Scenario::default();
+    s.potential.enabled = true;
+    s.potential.terms = vec![Builtin::Harmonic(Harmonic { strength: 8. }).into()];
+    let h_bar = s.constants.h_bar();
+
+    // the harmonic ground state is a gaussian at rest, which has the minimum uncertainty
+    let potential = potential_vector(&s);
+    let ground = &lowest_states(1, &potential, &s)[0];
+    let o = Observables::compute(&ground.psi, &potential, &s);
+    assert!((o.energy() - ground.energy).abs() / ground.energy < 1e-6);
+    assert!(o.x.abs() < 1e-10 && o.p.abs() < 1e-10);
+    assert!((o.uncertainty() - h_bar / 2.).abs() / h_bar < 1e-3);
+    // the virial theorem splits the energy evenly between the kinetic and potential part
+    assert!((o.kinetic - o.potential).abs() / o.energy() < 1e-3);
+
+    // the initial packet moves to the right and can not beat the uncertainty principle
+    let o = Observables::compute(&wave(&s).unwrap().1, &potential, &s);
+    assert!(o.p > 0.);
+    assert!(o.uncertainty() >= h_bar / 2.);
 }
 
 #[test]
-fn complex_display() {
-    assert_eq!(format!("{}", 5. + 2. * i()), "5+2i");
-    assert_eq!(format!("{}", 5. - 2. * i()), "5-2i");
+fn momentum_distribution() {
+    let s = Scenario::default();
+    let psi = wave(&s).unwrap().1;
+    let (k, phi) = momentum_space(&psi, &s);
+    let dk = k[1] - k[0];
+
+    // the distribution is centred on k_0 and carries the same probability as psi
+    let peak = (0..k.len())
+        .max_by(|a, b| {
+            phi[*a]
+                .abs_squared()
+                .partial_cmp(&phi[*b].abs_squared())
+                .unwrap()
+        })
+        .unwrap();
+    assert!((k[peak] - s.initial.k_0).abs() <= dk);
+    let norm_k = phi.iter().map(|p| p.abs_squared()).sum::<f64>() * dk;
+    let norm_x = psi.iter().map(|p| p.abs_squared()).sum::<f64>() * s.grid.spacing;
+    assert!((norm_k - norm_x).abs() < 1e-10);
 }
 
 #[test]
-fn exponential() {
-    assert!((Complex::exp(0. - i() * PI) - Complex::new(-1., 0.)).abs_squared() < 1e-30);
+fn initial_state_builder() {
+    let mut s = Scenario::default();
+    let h_bar = s.constants.h_bar();
+
+    // sigma is the width of |psi|^2, and the packet moves with hbar*k_0
+    let psi = WaveBuilder::new()
+        .gaussian(-1., 0.3, 12.)
+        .build_1d(&s)
+        .unwrap();
+    let o = Observables::compute(&psi, &potential_vector(&s), &s);
+    assert!((o.norm - 1.).abs() < 1e-10);
+    assert!((o.x + 1.).abs() < 1e-10);
+    assert!((o.delta_x() - 0.3).abs() < 1e-6);
+    // the central difference measures sin(k*dx)/dx rather than k
+    assert!((o.p - h_bar * 12.).abs() / o.p < 5e-3);
+
+    // two equal packets on either side of the origin
+    let psi = WaveBuilder::new()
+        .gaussian(-2., 0.3, 10.)
+        .gaussian(2., 0.3, -10.)
+        .build_1d(&s)
+        .unwrap();
+    let o = Observables::compute(&psi, &potential_vector(&s), &s);
+    assert!(o.x.abs() < 1e-10 && o.p.abs() < 1e-10);
+
+    // components from the scenario, an eigenstate of the potential stays one
+    s.potential.enabled = true;
+    s.initial.components = vec![Component {
+        amplitude: 2.,
+        ..Component::new(Shape::Eigenstate { n: 2, n_z: 0 })
+    }];
+    let psi = wave(&s).unwrap().1;
+    let states = lowest_states(3, &potential_vector(&s), &s);
+    let coefficients = expand(&psi, &states, &s);
+    let c_2 = coefficients[2].abs_squared();
+    assert!((c_2 / inner_product(&psi, &psi, &s).real() - 1.).abs() < 1e-10);
+    assert!(coefficients[0].abs_squared() / c_2 < 1e-16);
+    assert!(coefficients[1].abs_squared() / c_2 < 1e-16);
+
+    // any closure can be sampled, and the weights of the parts are kept relative to each other
+    let psi = WaveBuilder::new()
+        .function(Complex::from_real(1.), |x| {
+            Complex::exp(i() * (3. * PI * x / 8.))
+        })
+        .function(Complex::new(0., 1.), |x| (PI * x / 8.).cos().into())
+        .build_1d(&s)
+        .unwrap();
+    // at x = 0 this is (1/sqrt(8) + i/2) / sqrt(2), since the two parts are orthogonal
+    let expected = Complex::new(0.25, 0.5 / 2f64.sqrt());
+    assert!((psi[400] - expected).abs_squared() < 1e-6);
+
+    // states that can't be built are errors
+    let cosine = |x: f64| Complex::from_real(x.cos());
+    let builders = [
+        WaveBuilder::new().function(Complex::from_real(1.), |_| Complex::zero()),
+        WaveBuilder::new()
+            .function(Complex::from_real(1.), cosine)
+            .function(Complex::from_real(-1.), cosine),
+        WaveBuilder::new().eigenstate(s.grid.points().len()),
+        WaveBuilder::new().function_2d(Complex::from_real(1.), move |x, z| cosine(x * z)),
+    ];
+    for builder in builders {
+        assert!(builder.build_1d(&s).is_err());
+    }
 }
 
 #[test]
-fn assign() {
-    let mut a = 2. - 5. * i();
-    a += -4. + 9. * i();
-    assert_eq!(a, -2. + 4. * i());
-
-    let mut s = 2. - 5. * i();
-    s -= -4. + 9. * i();
-    assert_eq!(s, 6. - 14. * i());
-
-    let mut m = 2. - 5. * i();
-    m *= -4. + 9. * i();
-    assert_eq!(m, 37. + 38. * i());
-
-    let mut d = 2. - 5. * i();
-    d /= -4. + 9. * i();
-    assert_eq!(d, -53. / 97. + 2. / 97. * i());
+fn driving_field() {
+    let mut s = Scenario::default();
+    s.potential.enabled = true;
+    s.potential.terms = Vec::new();
+    s.potential.driving = vec![DrivingTerm::ElectricField(ElectricField {
+        strength: 1.,
+        frequency: 10.,
+        phase: 0.,
+        envelope: Envelope::Constant,
+    })];
+    s.initial.components = vec![Component::new(Shape::Gaussian {
+        x_0: 0.,
+        sigma: 0.5,
+        k_0: 0.,
+        z_0: 0.,
+        k_z: 0.,
+    })];
+    let potential = potential_vector(&s);
+
+    // by Ehrenfest's theorem d<p>/dt = -E(t), so <p> = -strength * sin(frequency * t) / frequency,
+    // which only comes out right if every step sees the field at the right time
+    let steps = 400;
+    let expected = -(10. * steps as f64 * s.time.dt).sin() / 10.;
+    for integrator in [
+        Integrator::RungeKutta4,
+        Integrator::CrankNicolson,
+        Integrator::SplitOperator,
+    ] {
+        s.time.integrator = integrator;
+        let mut psi = wave(&s).unwrap().1;
+        for step in 0..steps {
+            psi = iter_dt(&psi, &potential, step as f64 * s.time.dt, &s);
+        }
+        assert!(
+            (Observables::compute(&psi, &potential, &s).p - expected).abs() / expected.abs() < 1e-3
+        );
+    }
+
+    // a kick changes the momentum by the given amount and then leaves the packet alone
+    s.potential.driving = vec![DrivingTerm::Kick(Kick {
+        momentum: 0.5,
+        start: 0.01,
+        duration: 0.05,
+    })];
+    let mut psi = wave(&s).unwrap().1;
+    for step in 0..steps {
+        psi = iter_dt(&psi, &potential, step as f64 * s.time.dt, &s);
+    }
+    assert!((Observables::compute(&psi, &potential, &s).p - 0.5).abs() < 1e-3);
 }
 
-// iteration testing
 #[test]
-#[allow(non_snake_case)]
-fn oneD_iter() {
-    let wave0 = wave();
+fn scattering() {
+    let mut s = Scenario::default();
+    s.grid.length = 12.;
+    s.time.integrator = Integrator::CrankNicolson;
+    s.time.dt = 0.001;
+    s.potential.enabled = true;
+    s.potential.terms = vec![Builtin::Barrier(Barrier {
+        start: 0.,
+        end: 0.5,
+        height: 1.,
+    })
+    .into()];
+    let potential = potential_vector(&s);
+    let (h_bar, m) = (s.constants.h_bar(), s.constants.m);
 
-    // iteration with matrix multiplication is reliable but horribly slow
-    // Serves as a good test reference for the faster vector iteration rk4 method
-    // that is trickier to get right.
-    let size = wave0.0.len();
-    let T = descrete_derivative_matrix(size);
+    // the transfer matrix reproduces the rectangular barrier, as wide as the cells inside of it
+    let width = potential.iter().filter(|v| v.real() > 0.).count() as f64 * s.grid.spacing;
+    for energy in [0.3, 0.9, 1.2, 2.5] {
+        let kappa = (2. * m * (energy - 1.)).abs().sqrt() / h_bar * width;
+        let wall = if energy < 1. {
+            kappa.sinh().powi(2)
+        } else {
+            kappa.sin().powi(2)
+        };
+        let exact = 1. / (1. + wall / (4. * energy * (energy - 1.)).abs());
+        let t = transmission(energy, &potential, &s).unwrap();
+        assert!((t - exact).abs() < 1e-10);
+    }
+    // a step lets through 4*k_1*k_2/(k_1+k_2)^2 of the probability current
+    let mut step = s.clone();
+    step.potential.terms = vec![Builtin::Step(Step {
+        position: 0.,
+        height: 1.,
+    })
+    .into()];
+    let (k_1, k_2) = (2f64.sqrt(), 1.);
+    let t = transmission(2., &potential_vector(&step), &step).unwrap();
+    assert!((t - 4. * k_1 * k_2 / (k_1 + k_2).powi(2)).abs() < 1e-10);
+
+    // a packet from either side is split up the way T(E) predicts for its energies
+    s.boundary.absorbing = Some(AbsorbingLayer {
+        width: 2.,
+        strength: 10.,
+    });
+    for (x_0, k_0) in [(-2.5, 10.), (3., -10.)] {
+        s.initial.components = vec![Component::new(Shape::Gaussian {
+            x_0,
+            sigma: 0.5,
+            k_0,
+            z_0: 0.,
+            k_z: 0.,
+        })];
+        let psi = wave(&s).unwrap().1;
+        let result = scatter(&psi, &potential, &s, 1e-4, 20_000).unwrap();
+        let expected = packet_transmission(&psi, &potential, &s).unwrap();
+        // the finite differences see slightly lower energies than the continuum does
+        assert!((result.transmitted - expected).abs() < 5e-3);
+        assert!((result.sum() - 1.).abs() < 1e-3);
+    }
+}
 
-    let V = descrete_potential_matrix(Box::new(v));
+#[test]
+fn simulation_api() {
+    let mut s = Scenario::default();
+    s.time.integrator = Integrator::SplitOperator;
+    let mut simulation = Simulation::new(s.clone(), 1).unwrap();
+    simulation.run(20);
 
-    let U = (DT / Complex::new(0., H_BAR)) * (&T + &V);
-    let iter_matrix = rk4_matrix_mul(&wave0.1, &U);
+    // the same as calling the propagator of the scenario directly
+    let potential = potential_vector(&s);
+    let mut psi = wave(&s).unwrap().1;
+    for step in 0..20 {
+        psi = iter_dt(&psi, &potential, step as f64 * s.time.dt, &s);
+    }
+    assert_eq!(simulation.wave_function().as_1d(), Some(&psi));
+    assert_eq!(simulation.time(), 20. * s.time.dt);
+    let observables = Observables::compute(&psi, &potential, &s);
+    // a plain sum rather than Simpson's rule, on a coarse grid
+    assert!((simulation.norm() - observables.norm).abs() < 1e-5);
+    // the observables take <p^2> from central differences rather than the three point stencil
+    assert!((simulation.energy() - observables.energy()).abs() / observables.energy() < 2e-3);
 
-    let iter_vector = rk4_iter_dt(&wave0.1);
+    // a checkpoint continues the same run
+    let mut resumed = Simulation::from_checkpoint(&simulation.checkpoint()).unwrap();
+    simulation.run(5);
+    resumed.run(5);
+    assert_eq!(resumed.wave_function(), simulation.wave_function());
+    assert_eq!(resumed.step(), 25);
 
-    for i in 0..size {
-        // the resulting values should be equal (with some leeway for floating point errors)
-        assert!(iter_matrix[i].abs_squared() - iter_vector[i].abs_squared() < 1e-15)
+    // resuming doesn't build the initial state again, which here couldn't be built at all
+    let mut unbuildable = s.clone();
+    let n = s.grid.points().len();
+    unbuildable.initial.components = vec![Component::new(Shape::Eigenstate { n, n_z: 0 })];
+    assert!(Simulation::<f64>::new(unbuildable.clone(), 1).is_err());
+    let checkpoint = Checkpoint::from_1d(25, simulation.time(), &unbuildable, &psi);
+    assert!(Simulation::<f64>::from_checkpoint(&checkpoint).is_ok());
+
+    assert!(Simulation::<f64>::new(s, 3).is_err());
+}
+
+#[test]
+fn single_precision() {
+    // every integrator stays close to the f64 run
+    for integrator in [
+        Integrator::RungeKutta4,
+        Integrator::CrankNicolson,
+        Integrator::SplitOperator,
+    ] {
+        let mut s = Scenario::default();
+        s.time.integrator = integrator;
+        let mut single = Simulation::<f32>::new(s.clone(), 1).unwrap();
+        let mut double = Simulation::<f64>::new(s, 1).unwrap();
+        single.run(50);
+        double.run(50);
+
+        let single = single.wave_function().as_1d().unwrap().map(Complex::cast);
+        let double = double.wave_function().as_1d().unwrap();
+        let largest = double.iter().map(|p| p.abs()).fold(0., f64::max);
+        let error = (single - double).iter().map(|p| p.abs()).fold(0., f64::max);
+        assert!(error / largest < 1e-5, "{integrator:?}: {error}");
     }
 }
+
+#[test]
+fn rk4_workspace() {
+    // driven and periodic, on a grid of more than one chunk, so the fused stages have to get the
+    // time of every stage, the wrapped neighbours and the chunk edges right
+    let mut s = Scenario::default();
+    s.grid.spacing = 0.0015;
+    s.boundary.periodic = true;
+    s.potential.enabled = true;
+    s.potential.terms = vec![Builtin::Harmonic(Harmonic { strength: 5. }).into()];
+    s.potential.driving = vec![DrivingTerm::ElectricField(ElectricField {
+        strength: 1.,
+        frequency: 10.,
+        phase: 0.,
+        envelope: Envelope::Constant,
+    })];
+    s.time.integrator = Integrator::RungeKutta4;
+    s.time.dt = 1e-5;
+    assert!(s.grid.points().len() > 4096);
+    let potential = potential_vector(&s);
+
+    // every stage has to see H at its own time
+    let h = |f: &[Complex], t: f64| {
+        let f = DVector::from_column_slice(f);
+        hamiltonian(&f, &potential_at(&potential, t, &s), &s)
+            .as_slice()
+            .to_vec()
+    };
+    let mut expected = wave(&s).unwrap().1;
+    let mut psi = expected.clone();
+    let mut workspace = Rk4Workspace::default();
+    for step in 0..5 {
+        let t = step as f64 * s.time.dt;
+        let next = rk4_reference(expected.as_slice(), t, s.time.dt, s.constants.h_bar(), h);
+        expected = DVector::from_vec(next);
+        iter_dt_in_place(&mut psi, &potential, t, &s, &mut workspace);
+        assert!((&psi - &expected).iter().all(|d| d.abs() < 1e-12));
+    }
+
+    // the same workspace adjusts to a smaller grid
+    s.grid.spacing = 0.01;
+    let psi0 = wave(&s).unwrap().1;
+    let mut psi = psi0.clone();
+    iter_dt_in_place(&mut psi, &potential_vector(&s), 0., &s, &mut workspace);
+    assert_eq!(psi, rk4_iter_dt(&psi0, &potential_vector(&s), 0., &s));
+}
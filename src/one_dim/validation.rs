@@ -0,0 +1,215 @@
+// Checks the propagators and the eigen solver against closed form solutions, and measures how
+// quickly their errors shrink with DX and DT. The observed orders are printed as they are
+// checked, so `cargo test validation -- --nocapture` doubles as a report on the numerics.
+use std::f64::consts::PI;
+
+use nalgebra::DVector;
+
+use super::{
+    eigen::lowest_states,
+    iteration::{iter_dt, potential_vector},
+    observables::Observables,
+};
+use crate::{
+    complex::*,
+    potential::Harmonic,
+    scenario::{Builtin, Grid, Integrator, Scenario},
+};
+
+// A gaussian with |psi|^2 of width sigma at x_0 and momentum hbar*k_0, spreading freely.
+// With tau = hbar*t/(2m*sigma^2) it is
+// psi(x, t) = (2*pi*sigma^2)^(-1/4) / sqrt(1 + i*tau)
+//     * e^((-(x-x_0)^2/(4*sigma^2) + i*k_0*(x-x_0) - i*tau*sigma^2*k_0^2) / (1 + i*tau))
+fn free_gaussian(x: f64, t: f64, x_0: f64, sigma: f64, k_0: f64, s: &Scenario) -> Complex {
+    let tau = s.constants.h_bar() * t / (2. * s.constants.m * sigma.powi(2));
+    let denominator = 1. + tau * i();
+    let exponent = -(x - x_0).powi(2) / (4. * sigma.powi(2)) + i() * (k_0 * (x - x_0))
+        - i() * (tau * sigma.powi(2) * k_0.powi(2));
+    (2. * PI * sigma.powi(2)).powf(-0.25) / denominator.sqrt() * (exponent / denominator).exp()
+}
+
+// The coherent state of V = strength*x^2 that starts at rest at x_0, i.e. the ground state
+// moved to x_0. It keeps its shape and follows the classical path x_0*cos(wt), so
+// |psi|^2 is a gaussian of width sqrt(hbar/(2mw)) around it.
+fn coherent_density(x: f64, t: f64, x_0: f64, strength: f64, s: &Scenario) -> f64 {
+    let m = s.constants.m;
+    let w = (2. * strength / m).sqrt();
+    let sigma = (s.constants.h_bar() / (2. * m * w)).sqrt();
+    let centre = x_0 * (w * t).cos();
+    (-(x - centre).powi(2) / (2. * sigma.powi(2))).exp() / (2. * PI * sigma.powi(2)).sqrt()
+}
+
+// psi at t = 0, which is real since the packet starts at rest
+fn coherent_start(x_0: f64, strength: f64, s: &Scenario) -> DVector<Complex> {
+    sample(s, |x| {
+        coherent_density(x, 0., x_0, strength, s).sqrt().into()
+    })
+}
+
+fn sample(s: &Scenario, f: impl Fn(f64) -> Complex) -> DVector<Complex> {
+    DVector::from(s.grid.points().into_iter().map(f).collect::<Vec<Complex>>())
+}
+
+fn harmonic(strength: f64) -> Scenario {
+    let mut s = Scenario::default();
+    s.potential.enabled = true;
+    s.potential.terms = vec![Builtin::Harmonic(Harmonic { strength }).into()];
+    s
+}
+
+// psi0 taken from 0 to t with the integrator of the scenario
+fn evolve(psi0: &DVector<Complex>, t: f64, s: &Scenario) -> DVector<Complex> {
+    let potential = potential_vector(s);
+    let steps = (t / s.time.dt).round() as usize;
+    let mut psi = psi0.clone();
+    for step in 0..steps {
+        psi = iter_dt(&psi, &potential, step as f64 * s.time.dt, s);
+    }
+    psi
+}
+
+// sqrt(int{|a - b|^2}dx)
+fn distance(a: &DVector<Complex>, b: &DVector<Complex>, s: &Scenario) -> f64 {
+    ((a - b).iter().map(|d| d.abs_squared()).sum::<f64>() * s.grid.spacing).sqrt()
+}
+
+// The observed orders p from errors that were taken with the step halved each time,
+// i.e. error ~ step^p. Prints them with the name of what was measured.
+fn orders(name: &str, errors: &[f64]) -> Vec<f64> {
+    let res = errors
+        .windows(2)
+        .map(|pair| (pair[0] / pair[1]).log2())
+        .collect::<Vec<f64>>();
+    let list = |values: &[f64], f: fn(&f64) -> String| {
+        values.iter().map(f).collect::<Vec<String>>().join(", ")
+    };
+    println!(
+        "{name}: errors {}, observed orders {}",
+        list(errors, |e| format!("{e:.3e}")),
+        list(&res, |p| format!("{p:.2}"))
+    );
+    res
+}
+
+#[test]
+fn free_gaussian_spreading() {
+    let (x_0, sigma, k_0, t): (f64, f64, f64, f64) = (-1., 0.3, 10., 0.5);
+    let mut s = Scenario::default();
+    s.time.integrator = Integrator::CrankNicolson;
+    s.time.dt = 1e-4;
+
+    let (h_bar, m) = (s.constants.h_bar(), s.constants.m);
+    let tau = h_bar * t / (2. * m * sigma.powi(2));
+
+    // dt is small enough for the error of the stencil to dominate
+    let mut errors = Vec::new();
+    for spacing in [0.04, 0.02, 0.01] {
+        s.grid.spacing = spacing;
+        let psi0 = sample(&s, |x| free_gaussian(x, 0., x_0, sigma, k_0, &s));
+        let psi = evolve(&psi0, t, &s);
+        let exact = sample(&s, |x| free_gaussian(x, t, x_0, sigma, k_0, &s));
+        errors.push(distance(&psi, &exact, &s));
+
+        // moves with hbar*k_0/m and spreads to sigma*sqrt(1 + tau^2)
+        let o = Observables::compute(&psi, &potential_vector(&s), &s);
+        assert!((o.x - (x_0 + h_bar * k_0 * t / m)).abs() < 0.03);
+        assert!((o.delta_x() / (sigma * (1. + tau.powi(2)).sqrt()) - 1.).abs() < 0.02);
+    }
+    assert!(errors[2] < 5e-3);
+    for order in orders("free gaussian, Crank-Nicolson in DX", &errors) {
+        assert!((order - 2.).abs() < 0.1);
+    }
+
+    // the kinetic factor of the split operator is exact for a free particle, and the fft
+    // is exact for anything the grid resolves, so any dt will do
+    s.time.integrator = Integrator::SplitOperator;
+    s.time.dt = 0.05;
+    let psi0 = sample(&s, |x| free_gaussian(x, 0., x_0, sigma, k_0, &s));
+    let exact = sample(&s, |x| free_gaussian(x, t, x_0, sigma, k_0, &s));
+    let error = distance(&evolve(&psi0, t, &s), &exact, &s);
+    println!("free gaussian, split operator: error {error:.3e}");
+    assert!(error < 1e-10);
+}
+
+#[test]
+fn coherent_state() {
+    // w = 2*pi, so the packet is back at the other side after half a period
+    let (x_0, strength) = (1., 2. * PI.powi(2));
+    let mut s = harmonic(strength);
+    s.time.integrator = Integrator::SplitOperator;
+    let sigma = (s.constants.h_bar() / (2. * s.constants.m * 2. * PI)).sqrt();
+
+    let mut psi = coherent_start(x_0, strength, &s);
+    let mut t = 0.;
+    for end in [0.1, 0.25, 0.5] {
+        psi = evolve(&psi, end - t, &s);
+        t = end;
+        let exact = sample(&s, |x| coherent_density(x, t, x_0, strength, &s).into());
+        let density = psi.map(|p| p.abs_squared().into());
+        assert!(distance(&density, &exact, &s) < 1e-4);
+
+        // the classical path, without spreading. <p> comes from central differences, which
+        // are off by about (k*dx)^2/6 at the k = m*w*x_0/hbar = 40 of the packet
+        let o = Observables::compute(&psi, &potential_vector(&s), &s);
+        let p_max = s.constants.m * 2. * PI * x_0;
+        assert!((o.x - x_0 * (2. * PI * t).cos()).abs() < 1e-4);
+        assert!((o.p + p_max * (2. * PI * t).sin()).abs() / p_max < 0.03);
+        assert!((o.delta_x() - sigma).abs() / sigma < 1e-3);
+    }
+}
+
+#[test]
+fn orders_in_dt() {
+    let (x_0, strength, t) = (1., 2. * PI.powi(2), 0.25);
+    for (integrator, dt, expected) in [
+        (Integrator::RungeKutta4, 1e-3, 4.),
+        (Integrator::CrankNicolson, 1e-3, 2.),
+        (Integrator::SplitOperator, 1e-3, 2.),
+    ] {
+        let mut s = harmonic(strength);
+        // 243 = 3^5 points, which keeps the fft of the split operator fast
+        s.grid = Grid {
+            length: 7.5625,
+            spacing: 0.03125,
+        };
+        s.time.integrator = integrator;
+        let psi0 = coherent_start(x_0, strength, &s);
+
+        // the differences between successive halvings of dt, which leaves out the error in DX
+        let runs = [dt, dt / 2., dt / 4., dt / 8.]
+            .iter()
+            .map(|dt| {
+                s.time.dt = *dt;
+                evolve(&psi0, t, &s)
+            })
+            .collect::<Vec<_>>();
+        let errors = runs
+            .windows(2)
+            .map(|pair| distance(&pair[0], &pair[1], &s))
+            .collect::<Vec<f64>>();
+        for order in orders(&format!("coherent state, {integrator:?} in DT"), &errors) {
+            assert!((order - expected).abs() < 0.2);
+        }
+    }
+}
+
+#[test]
+fn harmonic_eigenenergies() {
+    // V = 8x^2 = m*w^2*x^2/2 with w = 4, so E_n = hbar*w*(n + 1/2)
+    let mut s = harmonic(8.);
+    let h_bar_w = s.constants.h_bar() * 4.;
+
+    let mut errors = vec![Vec::new(); 4];
+    for spacing in [0.04, 0.02, 0.01] {
+        s.grid.spacing = spacing;
+        let states = lowest_states(4, &potential_vector(&s), &s);
+        for (n, state) in states.iter().enumerate() {
+            errors[n].push((state.energy - h_bar_w * (n as f64 + 0.5)).abs());
+        }
+    }
+    for (n, errors) in errors.iter().enumerate() {
+        for order in orders(&format!("harmonic E_{n} in DX"), errors) {
+            assert!((order - 2.).abs() < 0.1);
+        }
+    }
+}
@@ -6,19 +6,48 @@ use bevy::{
         AccessibilityNode,
     },
     diagnostic::{DiagnosticsStore, FrameTimeDiagnosticsPlugin},
-    input::keyboard::KeyboardInput,
+    input::{keyboard::KeyboardInput, ButtonState},
     prelude::*,
     sprite::{MaterialMesh2dBundle, Mesh2dHandle},
 };
 use nalgebra::DVector;
+use std::path::PathBuf;
 
-use super::{iteration::rk4_iter_dt, v, wave, DT, DX, L, POTENTIAL};
-use crate::complex::Complex;
+use super::{
+    iteration::{iter_dt_in_place, potential_at, potential_vector},
+    momentum_space,
+    observables::Observables,
+    wave,
+};
+use crate::{
+    checkpoint::Checkpoint,
+    complex::Complex,
+    export::{Exporter, Frame},
+    scenario::Scenario,
+    utils::Rk4Workspace,
+};
 
-// creates bevy application and initiates simulation for one dimension
-pub fn oneD() {
+// the momentum space chart is drawn below the position space one, at this height
+const MOMENTUM_OFFSET: f32 = -2.5;
+// run makes sure that the initial state can be built before the app starts
+const CHECKED: &str = "The initial state was checked before the app started";
+
+// creates bevy application and initiates simulation for one dimension,
+// pressing E saves the current frame with the exporter,
+// S saves a checkpoint to checkpoint and L loads it again
+pub fn oneD(
+    scenario: Scenario,
+    exporter: Exporter,
+    checkpoint: PathBuf,
+    resume: Option<Checkpoint>,
+) {
     App::new()
         .add_plugins((DefaultPlugins, FrameTimeDiagnosticsPlugin))
+        .insert_resource(SampledPotential(potential_vector(&scenario)))
+        .insert_resource(Settings(scenario))
+        .insert_resource(Export(exporter))
+        .insert_resource(CheckpointFile(checkpoint))
+        .insert_resource(Resume(resume))
         .add_event::<ResetEvent>()
         // setup data
         .add_systems(Startup, setup)
@@ -27,7 +56,14 @@ pub fn oneD() {
         // update parameters and options after each frame
         .add_systems(
             PostUpdate,
-            (update_wave_function, update_params, update_options),
+            (
+                update_wave_function,
+                update_observables.after(update_wave_function),
+                update_params,
+                update_options,
+                export_frame,
+                save_load,
+            ),
         )
         .add_systems(PostUpdate, (listen_reset, read_reset))
         .run();
@@ -40,16 +76,43 @@ struct Data {
     raw: DVector<Complex>,
     prob: DVector<f32>,
     x: DVector<f32>,
+    // |phi(k)|^2 and the k values it is drawn at, already scaled to fit on the screen
+    momentum: DVector<f32>,
+    k: DVector<f32>,
+    // keeps the height of the momentum chart fixed at that of the initial distribution
+    momentum_scale: f32,
     speed: usize,
     time_passed: f64,
 }
 
+// the scenario that is being simulated
+#[derive(Resource)]
+struct Settings(Scenario);
+
+// the potential of the scenario on the grid, sampled once at the start
+#[derive(Resource)]
+struct SampledPotential(DVector<Complex>);
+
+// where frames are saved to
+#[derive(Resource)]
+struct Export(Exporter);
+
+// where checkpoints are saved to and loaded from
+#[derive(Resource)]
+struct CheckpointFile(PathBuf);
+
+// the state to start from instead of the initial wave function
+#[derive(Resource)]
+struct Resume(Option<Checkpoint>);
+
 #[derive(Component)]
 struct TimeText;
 #[derive(Component)]
 struct FrameText;
 #[derive(Component)]
 struct SpeedText;
+#[derive(Component)]
+struct ObservablesText;
 
 // holds informaiton about the buttons and which charts should be active
 #[derive(Component)]
@@ -77,6 +140,7 @@ impl ToggleButton {
 enum ToggleVariant {
     Real,
     Imag,
+    Momentum,
 }
 
 #[derive(Event)]
@@ -84,48 +148,80 @@ struct ResetEvent;
 #[derive(Component)]
 struct ResetButton;
 
-fn create_inital() -> Data {
-    let wave = wave();
-    let x = DVector::from(wave.0.iter().map(|x| *x as f32).collect::<Vec<f32>>());
-    let raw = wave.1.clone();
-    let prob = DVector::from(
-        wave.1
+fn create_inital(s: &Scenario) -> Data {
+    create_from(s, wave(s).expect(CHECKED).1, 0.)
+}
+
+// the data for psi at the given time, with the charts scaled as for the initial wave function
+fn create_from(s: &Scenario, raw: DVector<Complex>, time_passed: f64) -> Data {
+    let x = DVector::from(
+        s.grid
+            .points()
             .iter()
+            .map(|x| *x as f32)
+            .collect::<Vec<f32>>(),
+    );
+    let prob = DVector::from(
+        raw.iter()
             .map(|x| x.abs_squared() as f32)
             .collect::<Vec<f32>>(),
     );
 
+    // the chart covers -k_view..k_view, which fits the initial distribution as well as its reflection
+    let k_view = s.initial.k_0.abs() + 3. * s.initial.delta_k;
+    let (k, _) = momentum_space(&raw, s);
+    let momentum_scale = 1. / momentum_distribution(&wave(s).expect(CHECKED).1, s).max();
+
     Data {
+        momentum: momentum_distribution(&raw, s) * momentum_scale,
+        k: k.map(|k| (k / k_view * s.grid.length / 2.) as f32),
+        momentum_scale,
         raw,
         prob,
         x,
         speed: 1,
-        time_passed: 0.,
+        time_passed,
     }
 }
 
+// |phi(k)|^2 of psi
+fn momentum_distribution(psi: &DVector<Complex>, s: &Scenario) -> DVector<f32> {
+    momentum_space(psi, s).1.map(|p| p.abs_squared() as f32)
+}
+
 fn setup(
     mut commands: Commands,
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<ColorMaterial>>,
+    settings: Res<Settings>,
+    potential: Res<SampledPotential>,
+    resume: Res<Resume>,
 ) {
+    let s = &settings.0;
+
     // camera settings
     let mut camera = Camera2dBundle::default();
     camera.projection.scale = 0.01;
     commands.spawn(camera);
 
-    // initial wave packet
-    commands.spawn(create_inital());
+    // initial wave packet, or the one from the checkpoint
+    commands.spawn(match &resume.0 {
+        Some(checkpoint) => create_from(s, checkpoint.psi_1d(), checkpoint.time),
+        None => create_inital(s),
+    });
 
-    if POTENTIAL {
+    if s.potential.enabled {
         // show potential barriers
-        ((-L / (2. * DX)) as isize..=(L / (2. * DX)) as isize)
-            .map(|x| x as f64 * DX)
-            .for_each(|x| {
+        let dx = s.grid.spacing;
+        s.grid
+            .points()
+            .into_iter()
+            .zip(potential.0.iter())
+            .for_each(|(x, v)| {
                 commands.spawn(MaterialMesh2dBundle {
-                    mesh: Mesh2dHandle(meshes.add(Rectangle::new(DX as f32, v(x).real() as f32))),
+                    mesh: Mesh2dHandle(meshes.add(Rectangle::new(dx as f32, v.real() as f32))),
                     material: materials.add(Color::rgba(0.96, 0.59, 0.15, 0.46)),
-                    transform: Transform::from_xyz(x as f32, v(x).real() as f32 / 2., 0.),
+                    transform: Transform::from_xyz(x as f32, v.real() as f32 / 2., 0.),
                     ..default()
                 });
             })
@@ -181,6 +277,23 @@ fn setup(
                 SpeedText,
                 AccessibilityNode(NodeBuilder::new(Role::ListItem)),
             ));
+
+            // Expectation values of the current wave function
+            parent.spawn((
+                TextBundle::from_section(
+                    "",
+                    TextStyle {
+                        font_size: 20.,
+                        ..default()
+                    },
+                )
+                .with_style(Style {
+                    margin: UiRect::top(Val::Vh(2.)),
+                    ..default()
+                }),
+                ObservablesText,
+                AccessibilityNode(NodeBuilder::new(Role::ListItem)),
+            ));
         })
         .with_children(|parent| {
             parent
@@ -282,6 +395,43 @@ fn setup(
                     ));
                 });
 
+            // Show momentum chart button
+            parent
+                .spawn((
+                    ButtonBundle {
+                        style: Style {
+                            width: Val::Percent(65.),
+                            height: Val::Vw(3.),
+                            border: UiRect::all(Val::Px(5.0)),
+                            margin: UiRect::all(Val::Px(5.0)),
+                            // horizontally center child text
+                            justify_content: JustifyContent::Center,
+                            // vertically center child text
+                            align_items: AlignItems::Center,
+
+                            ..default()
+                        },
+                        border_color: Color::YELLOW.into(),
+                        background_color: Color::YELLOW.into(),
+                        ..default()
+                    },
+                    ToggleButton {
+                        variant: ToggleVariant::Momentum,
+                        active: true,
+                        color: Color::YELLOW.into(),
+                    },
+                    AccessibilityNode(NodeBuilder::new(Role::ListItem)),
+                ))
+                .with_children(|parent| {
+                    parent.spawn(TextBundle::from_section(
+                        "Momentum",
+                        TextStyle {
+                            font_size: 25.,
+                            ..default()
+                        },
+                    ));
+                });
+
             // reset button
             parent
                 .spawn((
@@ -314,14 +464,36 @@ fn setup(
                         },
                     ));
                 });
+            parent.spawn(TextBundle::from_section(
+                "S: save, L: load",
+                TextStyle {
+                    font_size: 20.,
+                    ..default()
+                },
+            ));
         });
 }
 fn draw_wave_function(
     mut gizmos: Gizmos,
     data: Query<&Data>,
     toggle_buttons_query: Query<&ToggleButton, With<Button>>,
+    settings: Res<Settings>,
+    potential: Res<SampledPotential>,
 ) {
     let data = data.get_single().unwrap();
+    let s = &settings.0;
+
+    if s.is_driven() {
+        // the bars only show the static part, so the full potential is drawn on top of them
+        let v = potential_at(&potential.0, data.time_passed, s);
+        for i in 0..data.x.len() - 1 {
+            gizmos.line_2d(
+                Vec2::new(data.x[i], v[i].real() as f32),
+                Vec2::new(data.x[i + 1], v[i + 1].real() as f32),
+                Color::ORANGE,
+            );
+        }
+    }
 
     for button in toggle_buttons_query.iter() {
         match button.variant() {
@@ -359,6 +531,34 @@ fn draw_wave_function(
                     }
                 }
             }
+            ToggleVariant::Momentum => {
+                if button.active() {
+                    gizmos.line_2d(
+                        Vec2::new(data.x[0], MOMENTUM_OFFSET),
+                        Vec2::new(data.x[data.x.len() - 1], MOMENTUM_OFFSET),
+                        Color::GRAY,
+                    );
+                    for i in 0..data.k.len() - 1 {
+                        // only the part of the chart that fits below the position space one
+                        if data.k[i].abs() > data.x[0].abs()
+                            || data.k[i + 1].abs() > data.x[0].abs()
+                        {
+                            continue;
+                        }
+                        gizmos.line_2d(
+                            Vec2 {
+                                x: data.k[i],
+                                y: MOMENTUM_OFFSET + data.momentum[i],
+                            },
+                            Vec2 {
+                                x: data.k[i + 1],
+                                y: MOMENTUM_OFFSET + data.momentum[i + 1],
+                            },
+                            Color::YELLOW,
+                        );
+                    }
+                }
+            }
         }
     }
 
@@ -377,14 +577,21 @@ fn draw_wave_function(
     }
 }
 
-fn update_wave_function(mut data: Query<&mut Data>) {
+fn update_wave_function(
+    mut data: Query<&mut Data>,
+    settings: Res<Settings>,
+    potential: Res<SampledPotential>,
+    mut workspace: Local<Rk4Workspace>,
+) {
+    let s = &settings.0;
     // iterate
     let mut data = data.get_single_mut().unwrap();
     let mut next = data.raw.clone();
     // skips to the next time step i.e. data.speed
     // each iteration is still calculated, but the ones in between are not shown
-    for _ in 0..data.speed {
-        next = rk4_iter_dt(&next);
+    for j in 0..data.speed {
+        let t = data.time_passed + j as f64 * s.time.dt;
+        iter_dt_in_place(&mut next, &potential.0, t, s, &mut workspace);
     }
 
     // calculate new values
@@ -393,11 +600,42 @@ fn update_wave_function(mut data: Query<&mut Data>) {
             .map(|x| x.abs_squared() as f32)
             .collect::<Vec<f32>>(),
     );
+    data.momentum = momentum_distribution(&next, s) * data.momentum_scale;
     data.raw = next;
     data.prob = next_prob;
-    data.time_passed += DT * data.speed as f64;
+    data.time_passed += s.time.dt * data.speed as f64;
+}
+
+fn update_observables(
+    data: Query<&Data>,
+    settings: Res<Settings>,
+    potential: Res<SampledPotential>,
+    mut text: Query<&mut Text, With<ObservablesText>>,
+) {
+    let data = data.get_single().unwrap();
+    let s = &settings.0;
+    let o = Observables::compute(
+        &data.raw,
+        &potential_at(&potential.0, data.time_passed, s),
+        s,
+    );
+    for mut text in &mut text {
+        text.sections[0].value = format!(
+            "Norm: {:.4}\n<x>: {:.4}\n<x^2>: {:.4}\n<p>: {:.4}\n<p^2>: {:.4}\n\
+             dx*dp: {:.4}\n<H>: {:.4}",
+            o.norm,
+            o.x,
+            o.x_squared,
+            o.p,
+            o.p_squared,
+            o.uncertainty(),
+            o.energy()
+        );
+    }
 }
 
+// bevy systems take their queries as long types, there is no way around that
+#[allow(clippy::type_complexity)]
 fn update_params(
     mut data: Query<&mut Data>,
     mut key_evs: EventReader<KeyboardInput>,
@@ -439,6 +677,68 @@ fn update_params(
     }
 }
 
+fn export_frame(
+    mut key_evs: EventReader<KeyboardInput>,
+    data: Query<&Data>,
+    settings: Res<Settings>,
+    export: Res<Export>,
+) {
+    let s = &settings.0;
+    for e in key_evs.read() {
+        if e.key_code == KeyCode::KeyE && e.state == ButtonState::Pressed {
+            let data = data.get_single().unwrap();
+            let step = (data.time_passed / s.time.dt).round() as usize;
+            let frame = Frame::from_1d(step, data.time_passed, &s.grid.points(), &data.raw);
+            match export.0.write_numbered(&frame) {
+                Ok(path) => println!("Saved frame to {}", path.display()),
+                Err(e) => eprintln!("{e}"),
+            }
+        }
+    }
+}
+
+// S saves the state to the checkpoint file and L loads it again, as long as it was
+// saved with the same scenario
+fn save_load(
+    mut key_evs: EventReader<KeyboardInput>,
+    mut data: Query<&mut Data>,
+    settings: Res<Settings>,
+    file: Res<CheckpointFile>,
+) {
+    let s = &settings.0;
+    for e in key_evs.read() {
+        if e.state != ButtonState::Pressed {
+            continue;
+        }
+        let mut data = data.get_single_mut().unwrap();
+        match e.key_code {
+            KeyCode::KeyS => {
+                let step = (data.time_passed / s.time.dt).round() as usize;
+                let checkpoint = Checkpoint::from_1d(step, data.time_passed, s, &data.raw);
+                match checkpoint.save(&file.0) {
+                    Ok(()) => println!("Saved checkpoint to {}", file.0.display()),
+                    Err(e) => eprintln!("{e}"),
+                }
+            }
+            KeyCode::KeyL => match Checkpoint::load(&file.0) {
+                Ok(checkpoint) if checkpoint.dims != 1 || checkpoint.scenario != *s => eprintln!(
+                    "The checkpoint {} is from a different scenario",
+                    file.0.display()
+                ),
+                Ok(checkpoint) => {
+                    let speed = data.speed;
+                    *data = create_from(s, checkpoint.psi_1d(), checkpoint.time);
+                    data.speed = speed;
+                    println!("Loaded checkpoint from {}", file.0.display());
+                }
+                Err(e) => eprintln!("{e}"),
+            },
+            _ => {}
+        }
+    }
+}
+
+#[allow(clippy::type_complexity)]
 fn update_options(
     mut interaction_query: Query<
         (&Interaction, &mut BackgroundColor, &mut ToggleButton),
@@ -446,41 +746,40 @@ fn update_options(
     >,
 ) {
     for (interaction, mut color, mut toggle_button) in &mut interaction_query {
-        match *interaction {
-            Interaction::Pressed => {
-                // change the state of the chart in question
-                toggle_button.toggle();
-
-                // change the colour of the button to match its state
-                if toggle_button.active() {
-                    *color = toggle_button.color();
-                } else {
-                    *color = Color::BLACK.into();
-                }
+        if *interaction == Interaction::Pressed {
+            // change the state of the chart in question
+            toggle_button.toggle();
+
+            // change the colour of the button to match its state
+            if toggle_button.active() {
+                *color = toggle_button.color();
+            } else {
+                *color = Color::BLACK.into();
             }
-            _ => {}
-        };
+        }
     }
 }
 
+#[allow(clippy::type_complexity)]
 fn listen_reset(
     interaction_query: Query<(&Interaction, &ResetButton), (Changed<Interaction>, With<Button>)>,
     mut ev_reset: EventWriter<ResetEvent>,
 ) {
     for (interaction, _reset_button) in &interaction_query {
-        match *interaction {
-            Interaction::Pressed => {
-                ev_reset.send(ResetEvent);
-            }
-            _ => {}
-        };
+        if *interaction == Interaction::Pressed {
+            ev_reset.send(ResetEvent);
+        }
     }
 }
 
-fn read_reset(mut ev_reset: EventReader<ResetEvent>, mut data: Query<&mut Data>) {
+fn read_reset(
+    mut ev_reset: EventReader<ResetEvent>,
+    mut data: Query<&mut Data>,
+    settings: Res<Settings>,
+) {
     for _e in ev_reset.read() {
         let mut data = data.get_single_mut().unwrap();
         // reset to initial conditions
-        *data = create_inital();
+        *data = create_inital(&settings.0);
     }
 }
@@ -0,0 +1,454 @@
+use std::{
+    f64::consts::{E, PI},
+    ops::Add,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::expression::Expression;
+
+// A real, time independent potential V(x). Potentials can be combined with +, as well as
+// scaled and shifted, e.g.
+//
+// let v = Harmonic { strength: 1. } + Barrier { start: -0.25, end: 0.25, height: 5. }.scaled(2.);
+//
+// In two dimensions almost every potential is V(x) + V_z(z). Most of them are walls along the
+// z-axis, which don't depend on z at all, so V_z defaults to zero. Potentials that can't be
+// split up like that override value_2d as well.
+pub trait Potential {
+    fn value(&self, x: f64) -> f64;
+
+    // the part of the 2D potential that only depends on z
+    fn value_z(&self, _z: f64) -> f64 {
+        0.
+    }
+
+    fn value_2d(&self, x: f64, z: f64) -> f64 {
+        self.value(x) + self.value_z(z)
+    }
+
+    // factor * V
+    fn scaled(self, factor: f64) -> Scaled<Self>
+    where
+        Self: Sized,
+    {
+        Scaled {
+            potential: self,
+            factor,
+        }
+    }
+
+    // V(x - offset), i.e. the potential moved to the right by offset
+    fn shifted(self, offset: f64) -> Shifted<Self>
+    where
+        Self: Sized,
+    {
+        Shifted {
+            potential: self,
+            offset,
+        }
+    }
+}
+
+// constant height between start and end
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Barrier {
+    pub start: f64,
+    pub end: f64,
+    pub height: f64,
+}
+impl Potential for Barrier {
+    fn value(&self, x: f64) -> f64 {
+        if x > self.start && x < self.end {
+            self.height
+        } else {
+            0.
+        }
+    }
+}
+
+// constant -depth between start and end
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Well {
+    pub start: f64,
+    pub end: f64,
+    pub depth: f64,
+}
+impl Potential for Well {
+    fn value(&self, x: f64) -> f64 {
+        if x > self.start && x < self.end {
+            -self.depth
+        } else {
+            0.
+        }
+    }
+}
+
+// strength * x^2, which depends on the distance to the origin in two dimensions
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Harmonic {
+    pub strength: f64,
+}
+impl Potential for Harmonic {
+    fn value(&self, x: f64) -> f64 {
+        self.strength * x.powi(2)
+    }
+
+    fn value_z(&self, z: f64) -> f64 {
+        self.value(z)
+    }
+}
+
+// depth * ((2x/separation)^2 - 1)^2, two minima at +-separation/2 with a barrier of
+// height depth between them
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct DoubleWell {
+    pub separation: f64,
+    pub depth: f64,
+}
+impl Potential for DoubleWell {
+    fn value(&self, x: f64) -> f64 {
+        self.depth * ((2. * x / self.separation).powi(2) - 1.).powi(2)
+    }
+}
+
+// depth * (1 - e^(-a(x-center)))^2, the anharmonic potential of a diatomic molecule
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Morse {
+    pub depth: f64,
+    pub a: f64,
+    pub center: f64,
+}
+impl Potential for Morse {
+    fn value(&self, x: f64) -> f64 {
+        self.depth * (1. - E.powf(-self.a * (x - self.center))).powi(2)
+    }
+}
+
+// -depth / cosh^2(x/width), a reflectionless well for the right depths
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct PoschlTeller {
+    pub depth: f64,
+    pub width: f64,
+}
+impl Potential for PoschlTeller {
+    fn value(&self, x: f64) -> f64 {
+        -self.depth / (x / self.width).cosh().powi(2)
+    }
+}
+
+// a lattice of barriers with the given width and height, centred on every multiple of period
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct KronigPenney {
+    pub period: f64,
+    pub width: f64,
+    pub height: f64,
+}
+impl Potential for KronigPenney {
+    fn value(&self, x: f64) -> f64 {
+        let distance = x - (x / self.period).round() * self.period;
+        if distance.abs() < self.width / 2. {
+            self.height
+        } else {
+            0.
+        }
+    }
+}
+
+// height everywhere to the right of position
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Step {
+    pub position: f64,
+    pub height: f64,
+}
+impl Potential for Step {
+    fn value(&self, x: f64) -> f64 {
+        if x > self.position {
+            self.height
+        } else {
+            0.
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Sum<A, B>(pub A, pub B);
+impl<A: Potential, B: Potential> Potential for Sum<A, B> {
+    fn value(&self, x: f64) -> f64 {
+        self.0.value(x) + self.1.value(x)
+    }
+
+    fn value_z(&self, z: f64) -> f64 {
+        self.0.value_z(z) + self.1.value_z(z)
+    }
+
+    fn value_2d(&self, x: f64, z: f64) -> f64 {
+        self.0.value_2d(x, z) + self.1.value_2d(x, z)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Scaled<P> {
+    potential: P,
+    factor: f64,
+}
+impl<P: Potential> Potential for Scaled<P> {
+    fn value(&self, x: f64) -> f64 {
+        self.factor * self.potential.value(x)
+    }
+
+    fn value_z(&self, z: f64) -> f64 {
+        self.factor * self.potential.value_z(z)
+    }
+
+    fn value_2d(&self, x: f64, z: f64) -> f64 {
+        self.factor * self.potential.value_2d(x, z)
+    }
+}
+
+// only moves the potential along x
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Shifted<P> {
+    potential: P,
+    offset: f64,
+}
+impl<P: Potential> Potential for Shifted<P> {
+    fn value(&self, x: f64) -> f64 {
+        self.potential.value(x - self.offset)
+    }
+
+    fn value_z(&self, z: f64) -> f64 {
+        self.potential.value_z(z)
+    }
+
+    fn value_2d(&self, x: f64, z: f64) -> f64 {
+        self.potential.value_2d(x - self.offset, z)
+    }
+}
+
+impl<P: Potential + ?Sized> Potential for Box<P> {
+    fn value(&self, x: f64) -> f64 {
+        (**self).value(x)
+    }
+
+    fn value_z(&self, z: f64) -> f64 {
+        (**self).value_z(z)
+    }
+
+    fn value_2d(&self, x: f64, z: f64) -> f64 {
+        (**self).value_2d(x, z)
+    }
+}
+
+impl<P: Potential + ?Sized> Potential for &P {
+    fn value(&self, x: f64) -> f64 {
+        (**self).value(x)
+    }
+
+    fn value_z(&self, z: f64) -> f64 {
+        (**self).value_z(z)
+    }
+
+    fn value_2d(&self, x: f64, z: f64) -> f64 {
+        (**self).value_2d(x, z)
+    }
+}
+
+// A potential given as a formula, where t is zero and y is the z coordinate of the 2D grid.
+// A formula that mixes x and y can't be split into V(x) + V_z(z), so it is evaluated as a whole
+// in two dimensions, and value and value_z are its cuts through the origin.
+impl Potential for Expression {
+    fn value(&self, x: f64) -> f64 {
+        self.eval(x, 0., 0.)
+    }
+
+    fn value_z(&self, z: f64) -> f64 {
+        self.eval(0., z, 0.) - self.eval(0., 0., 0.)
+    }
+
+    fn value_2d(&self, x: f64, z: f64) -> f64 {
+        self.eval(x, z, 0.)
+    }
+}
+
+// V_1 + V_2 for every pair of potentials
+macro_rules! impl_add {
+    ($($t:ident $(<$($g:ident),*>)?),*) => {$(
+        impl<$($($g: Potential,)*)? Rhs: Potential> Add<Rhs> for $t$(<$($g),*>)? {
+            type Output = Sum<Self, Rhs>;
+
+            fn add(self, rhs: Rhs) -> Self::Output {
+                Sum(self, rhs)
+            }
+        }
+    )*};
+}
+impl_add!(
+    Barrier,
+    Well,
+    Harmonic,
+    DoubleWell,
+    Morse,
+    PoschlTeller,
+    KronigPenney,
+    Step,
+    Expression,
+    Sum<A, B>,
+    Scaled<P>,
+    Shifted<P>
+);
+
+// A potential that changes over time, V(x, t). In two dimensions most of these only depend on x,
+// just like the walls among the time independent potentials, and formulas can use y as well.
+pub trait TimeDependent {
+    fn value_at(&self, x: f64, t: f64) -> f64;
+
+    // V(x, z, t) on the 2D grid
+    fn value_at_2d(&self, x: f64, _z: f64, t: f64) -> f64 {
+        self.value_at(x, t)
+    }
+}
+
+// a barrier of constant height moving with velocity, between start and end at t = 0
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct MovingBarrier {
+    pub start: f64,
+    pub end: f64,
+    pub height: f64,
+    pub velocity: f64,
+}
+impl TimeDependent for MovingBarrier {
+    fn value_at(&self, x: f64, t: f64) -> f64 {
+        Barrier {
+            start: self.start,
+            end: self.end,
+            height: self.height,
+        }
+        .value(x - self.velocity * t)
+    }
+}
+
+// a well whose depth oscillates as depth * (1 + amplitude * sin(frequency * t))
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct OscillatingWell {
+    pub start: f64,
+    pub end: f64,
+    pub depth: f64,
+    pub amplitude: f64,
+    pub frequency: f64,
+}
+impl TimeDependent for OscillatingWell {
+    fn value_at(&self, x: f64, t: f64) -> f64 {
+        let depth = self.depth * (1. + self.amplitude * (self.frequency * t).sin());
+        Well {
+            start: self.start,
+            end: self.end,
+            depth,
+        }
+        .value(x)
+    }
+}
+
+// A uniform field along x, V = E(t) * x with E(t) = strength * envelope(t) * cos(frequency * t + phase).
+// The force on the particle is -E(t), and a frequency of zero gives a static field.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ElectricField {
+    pub strength: f64,
+    #[serde(default)]
+    pub frequency: f64,
+    #[serde(default)]
+    pub phase: f64,
+    #[serde(default)]
+    pub envelope: Envelope,
+}
+impl ElectricField {
+    pub fn field(&self, t: f64) -> f64 {
+        self.strength * self.envelope.value(t) * (self.frequency * t + self.phase).cos()
+    }
+}
+impl TimeDependent for ElectricField {
+    fn value_at(&self, x: f64, t: f64) -> f64 {
+        self.field(t) * x
+    }
+}
+
+// The shape of a pulse over time, between zero and one
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "shape", rename_all = "kebab-case")]
+pub enum Envelope {
+    // always on
+    #[default]
+    Constant,
+    // e^(-((t-center)/width)^2)
+    Gaussian {
+        center: f64,
+        width: f64,
+    },
+    // sin^2(pi*(t-start)/duration) from start to start + duration, which turns on and off smoothly
+    SinSquared {
+        start: f64,
+        duration: f64,
+    },
+    // switched on from start to start + duration
+    Square {
+        start: f64,
+        duration: f64,
+    },
+}
+impl Envelope {
+    pub fn value(&self, t: f64) -> f64 {
+        match *self {
+            Self::Constant => 1.,
+            Self::Gaussian { center, width } => E.powf(-((t - center) / width).powi(2)),
+            Self::SinSquared { start, duration } => {
+                if t >= start && t < start + duration {
+                    (PI * (t - start) / duration).sin().powi(2)
+                } else {
+                    0.
+                }
+            }
+            Self::Square { start, duration } => {
+                if t >= start && t < start + duration {
+                    1.
+                } else {
+                    0.
+                }
+            }
+        }
+    }
+}
+
+// A constant force that is switched on from start to start + duration, which changes the
+// momentum of the particle by momentum. The duration should span a few time steps, as the
+// force is only seen at the times the integrator evaluates the potential.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Kick {
+    pub momentum: f64,
+    pub start: f64,
+    pub duration: f64,
+}
+impl TimeDependent for Kick {
+    fn value_at(&self, x: f64, t: f64) -> f64 {
+        ElectricField {
+            strength: -self.momentum / self.duration,
+            frequency: 0.,
+            phase: 0.,
+            envelope: Envelope::Square {
+                start: self.start,
+                duration: self.duration,
+            },
+        }
+        .value_at(x, t)
+    }
+}
+
+// y is zero in one dimension, and the z coordinate of the 2D grid
+impl TimeDependent for Expression {
+    fn value_at(&self, x: f64, t: f64) -> f64 {
+        self.eval(x, 0., t)
+    }
+
+    fn value_at_2d(&self, x: f64, z: f64, t: f64) -> f64 {
+        self.eval(x, z, t)
+    }
+}
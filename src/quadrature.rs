@@ -0,0 +1,147 @@
+use std::f64::consts::PI;
+
+// Numerical integration, both of values sampled at equally spaced points (the grids) and of
+// functions that can be evaluated anywhere. The sampled rules take the values at every point
+// from the first to the last one, spaced by dx, so n values span n - 1 intervals.
+
+// Exact for linear functions, with an error of O(dx^2)
+pub fn trapezoid(data: &[f64], dx: f64) -> f64 {
+    if data.len() < 2 {
+        return 0.;
+    }
+    let inner: f64 = data[1..data.len() - 1].iter().sum();
+    (inner + (data[0] + data[data.len() - 1]) / 2.) * dx
+}
+
+// Composite Simpson's 1/3 rule, exact for cubics with an error of O(dx^4). It needs an even
+// number of intervals, i.e. an odd number of points. With an even number of points the last
+// three intervals are taken with the 3/8 rule instead, which keeps the order.
+pub fn simpson(data: &[f64], dx: f64) -> f64 {
+    let intervals = data.len().saturating_sub(1);
+    match intervals {
+        0 => 0.,
+        1 => trapezoid(data, dx),
+        _ if intervals % 2 == 0 => simpson_third(data, dx),
+        _ => {
+            let split = intervals - 3;
+            simpson_third(&data[..=split], dx) + simpson_eighth(&data[split..], dx)
+        }
+    }
+}
+
+// Composite Simpson's 3/8 rule, which needs the number of intervals to be a multiple of three.
+// One or two intervals left over are taken with the 1/3 rule at the start, over four and two
+// intervals respectively, so this is O(dx^4) for any number of points.
+pub fn simpson_3_8(data: &[f64], dx: f64) -> f64 {
+    let intervals = data.len().saturating_sub(1);
+    if intervals < 2 {
+        return trapezoid(data, dx);
+    }
+    let rest = [0, 4, 2][intervals % 3];
+    simpson_third(&data[..=rest], dx) + simpson_eighth(&data[rest..], dx)
+}
+
+// dx/3 (f0 + 4f1 + 2f2 + 4f3 + ... + fn) for an even number of intervals
+fn simpson_third(data: &[f64], dx: f64) -> f64 {
+    if data.len() < 3 {
+        return 0.;
+    }
+    let last = data.len() - 1;
+    let inner: f64 = (1..last)
+        .map(|j| {
+            if j % 2 == 1 {
+                4. * data[j]
+            } else {
+                2. * data[j]
+            }
+        })
+        .sum();
+    (data[0] + inner + data[last]) * dx / 3.
+}
+
+// 3dx/8 (f0 + 3f1 + 3f2 + 2f3 + 3f4 + ... + fn) for a multiple of three intervals
+fn simpson_eighth(data: &[f64], dx: f64) -> f64 {
+    if data.len() < 4 {
+        return 0.;
+    }
+    let last = data.len() - 1;
+    let inner: f64 = (1..last)
+        .map(|j| {
+            if j % 3 == 0 {
+                2. * data[j]
+            } else {
+                3. * data[j]
+            }
+        })
+        .sum();
+    (data[0] + inner + data[last]) * 3. * dx / 8.
+}
+
+// Romberg integration of f over [a, b]. The trapezoid rule is taken with 1, 2, 4, ... intervals
+// and Richardson extrapolation removes one more even power of the step from the error each time.
+// Stops once two diagonal estimates agree to within tolerance, or gives the last estimate after
+// 2^20 intervals. Converges very quickly for smooth functions, and slowly for anything with kinks.
+pub fn romberg(f: impl Fn(f64) -> f64, a: f64, b: f64, tolerance: f64) -> f64 {
+    const MAX_LEVELS: usize = 20;
+    let mut h = b - a;
+    let mut previous = vec![h * (f(a) + f(b)) / 2.];
+    for level in 1..=MAX_LEVELS {
+        h /= 2.;
+        // only the new midpoints have to be evaluated
+        let midpoints: f64 = (0..1 << (level - 1))
+            .map(|j| f(a + (2 * j + 1) as f64 * h))
+            .sum();
+        let mut row = vec![previous[0] / 2. + h * midpoints];
+        for k in 1..=level {
+            let factor = 4f64.powi(k as i32);
+            row.push((factor * row[k - 1] - previous[k - 1]) / (factor - 1.));
+        }
+        if (row[level] - previous[level - 1]).abs() <= tolerance * row[level].abs().max(1.) {
+            return row[level];
+        }
+        previous = row;
+    }
+    previous[MAX_LEVELS]
+}
+
+// n point Gauss-Legendre quadrature of f over [a, b], exact for polynomials up to degree 2n - 1
+pub fn gauss_legendre(f: impl Fn(f64) -> f64, a: f64, b: f64, n: usize) -> f64 {
+    let (middle, half) = ((a + b) / 2., (b - a) / 2.);
+    legendre_nodes(n)
+        .into_iter()
+        .map(|(x, w)| w * f(middle + half * x))
+        .sum::<f64>()
+        * half
+}
+
+// The roots of the Legendre polynomial P_n on [-1, 1] together with their weights
+// 2 / ((1 - x^2) P_n'(x)^2). The roots are found with Newton's method, starting from the
+// usual approximation cos(pi(j - 1/4)/(n + 1/2)), and P_n comes from the recurrence
+// (k + 1) P_k+1 = (2k + 1) x P_k - k P_k-1.
+pub fn legendre_nodes(n: usize) -> Vec<(f64, f64)> {
+    let legendre = |x: f64| {
+        let (mut p, mut previous) = (1., 0.);
+        for k in 0..n {
+            let k = k as f64;
+            (p, previous) = (((2. * k + 1.) * x * p - k * previous) / (k + 1.), p);
+        }
+        // P_n and its derivative
+        (p, n as f64 * (x * p - previous) / (x * x - 1.))
+    };
+
+    (1..=n)
+        .map(|j| {
+            let mut x = (PI * (j as f64 - 0.25) / (n as f64 + 0.5)).cos();
+            for _ in 0..100 {
+                let (p, dp) = legendre(x);
+                let step = p / dp;
+                x -= step;
+                if step.abs() < 1e-15 {
+                    break;
+                }
+            }
+            let (_, dp) = legendre(x);
+            (x, 2. / ((1. - x * x) * dp * dp))
+        })
+        .collect()
+}
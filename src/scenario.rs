@@ -0,0 +1,584 @@
+use std::{
+    f64::consts::PI,
+    fs,
+    io::{Error, ErrorKind},
+    path::Path,
+};
+
+use serde::{Deserialize, Deserializer, Serialize};
+
+use crate::{
+    expression::{Expression, Variable},
+    potential::{
+        Barrier, DoubleWell, ElectricField, Harmonic, Kick, KronigPenney, Morse, MovingBarrier,
+        OscillatingWell, PoschlTeller, Potential, Step, TimeDependent, Well,
+    },
+};
+
+// Everything that defines a single run of the simulation. Scenarios are stored as toml files,
+// where every value that is left out falls back on the default for the number of dimensions.
+//
+// [constants]
+// h = 1.0
+// m = 1.0
+//
+// [grid]
+// length = 8.0
+// spacing = 0.01
+//
+// [time]
+// dt = 0.0005
+// integrator = "runge-kutta4" (or "crank-nicolson" and "split-operator")
+//
+// [initial]
+// k_0 = 10.0
+// k_range = 10
+// dk = 0.5
+// delta_k = 5.0
+// components = [
+//     { type = "gaussian", x_0 = -2.0, sigma = 0.3, k_0 = 10.0 },
+//     { type = "eigenstate", n = 1, amplitude = 0.5, phase = 1.57 },
+// ]
+//
+// [potential]
+// enabled = true
+// terms = [
+//     { type = "barrier", start = 2.5, end = 3.0, height = 1.0 },
+//     { type = "harmonic", strength = 1.0 },
+//     { type = "expression", value = "3*exp(-(x-1)^2/0.1)" },
+// ]
+// (the other types are well, double-well, morse, poschl-teller, kronig-penney and step,
+// see potential.rs for their parameters, and every term can also have a scale and a shift)
+// driving = [
+//     { type = "electric-field", strength = 5.0, frequency = 20.0, envelope = { shape = "sin-squared", start = 0.0, duration = 1.0 } },
+//     { type = "kick", momentum = 1.0, start = 0.5, duration = 0.01 },
+//     { type = "expression", value = "step(x-2.5)*step(3-x)*(1+sin(5*t))" },
+// ]
+// (as well as moving-barrier and oscillating-well, see expression.rs for what formulas can contain)
+//
+// [boundary]
+// periodic = false
+// absorbing = { width = 1.0, strength = 5.0 }
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Scenario {
+    pub constants: Constants,
+    pub grid: Grid,
+    pub time: Time,
+    pub initial: InitialState,
+    pub potential: PotentialSpec,
+    pub boundary: Boundary,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Constants {
+    // Planck's constant
+    pub h: f64,
+    // mass of the particle
+    pub m: f64,
+}
+impl Constants {
+    pub fn h_bar(&self) -> f64 {
+        self.h / (2. * PI)
+    }
+}
+
+// The simulated region is [-length/2, length/2] along every axis
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Grid {
+    pub length: f64,
+    // distance between neighbouring grid points
+    pub spacing: f64,
+}
+impl Grid {
+    // the positions of the grid points along one axis
+    pub fn points(&self) -> Vec<f64> {
+        (0..2 * self.half() + 1).map(|j| self.point(j)).collect()
+    }
+
+    // the position of grid point j, without sampling all of them
+    pub fn point(&self, j: usize) -> f64 {
+        (j as isize - self.half() as isize) as f64 * self.spacing
+    }
+
+    // the number of points on either side of zero
+    fn half(&self) -> usize {
+        (self.length / (2. * self.spacing)) as usize
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Time {
+    pub dt: f64,
+    // only used in one dimension, two dimensions always use RK4
+    pub integrator: Integrator,
+}
+
+// The available schemes for stepping the wave function forward in time
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Integrator {
+    // explicit fourth order Runge-Kutta, only stable for small DT compared to DX^2
+    RungeKutta4,
+    // implicit and unitary, stable for any DT
+    CrankNicolson,
+    // alternates between position and momentum space using the fft,
+    // spectrally accurate in space and allows for large DT
+    SplitOperator,
+}
+
+// The initial wave function is the superposition of the components. Without any components
+// it is psi = int{c(k)e^(ikx)}dk, where c(k) = e^(-((k-k_0)/delta_k)^2), and the integral is
+// approximated by summing over k = n*dk for n = k_0-k_range..=k_0+k_range.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct InitialState {
+    pub k_0: f64,
+    pub k_range: isize,
+    pub dk: f64,
+    pub delta_k: f64,
+    #[serde(default)]
+    pub components: Vec<Component>,
+}
+
+// A single part of the initial superposition. Every component is normalized on its own
+// before it is multiplied by amplitude*e^(i*phase), so only the relative amplitudes matter.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Component {
+    #[serde(flatten)]
+    pub shape: Shape,
+    #[serde(default = "unit_amplitude")]
+    pub amplitude: f64,
+    #[serde(default)]
+    pub phase: f64,
+}
+impl Component {
+    pub fn new(shape: Shape) -> Self {
+        Self {
+            shape,
+            amplitude: 1.,
+            phase: 0.,
+        }
+    }
+}
+
+fn unit_amplitude() -> f64 {
+    1.
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+pub enum Shape {
+    // e^(-(x-x_0)^2/(4*sigma^2)) e^(i*k_0*x), so sigma is the width of |psi|^2.
+    // In two dimensions this is multiplied by the same packet along z around z_0 with k_z.
+    Gaussian {
+        x_0: f64,
+        sigma: f64,
+        k_0: f64,
+        #[serde(default)]
+        z_0: f64,
+        #[serde(default)]
+        k_z: f64,
+    },
+    // the stationary state number n of the Hamiltonian, where 0 is the ground state.
+    // In two dimensions the state is the product of state n along x and state n_z along z.
+    Eigenstate {
+        n: usize,
+        #[serde(default)]
+        n_z: usize,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PotentialSpec {
+    pub enabled: bool,
+    pub terms: Vec<PotentialTerm>,
+    // the parts of the potential that change over time
+    #[serde(default)]
+    pub driving: Vec<DrivingTerm>,
+}
+
+// The potential is the sum of all of its terms. Each term is one of the built in potentials,
+// multiplied by scale and moved to the right by shift.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PotentialTerm {
+    #[serde(flatten)]
+    pub builtin: Builtin,
+    #[serde(default = "unit_amplitude")]
+    pub scale: f64,
+    #[serde(default)]
+    pub shift: f64,
+}
+impl From<Builtin> for PotentialTerm {
+    fn from(builtin: Builtin) -> Self {
+        Self {
+            builtin,
+            scale: 1.,
+            shift: 0.,
+        }
+    }
+}
+impl Potential for PotentialTerm {
+    fn value(&self, x: f64) -> f64 {
+        (&self.builtin)
+            .scaled(self.scale)
+            .shifted(self.shift)
+            .value(x)
+    }
+
+    fn value_z(&self, z: f64) -> f64 {
+        (&self.builtin)
+            .scaled(self.scale)
+            .shifted(self.shift)
+            .value_z(z)
+    }
+
+    fn value_2d(&self, x: f64, z: f64) -> f64 {
+        (&self.builtin)
+            .scaled(self.scale)
+            .shifted(self.shift)
+            .value_2d(x, z)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+pub enum Builtin {
+    Barrier(Barrier),
+    Well(Well),
+    Harmonic(Harmonic),
+    DoubleWell(DoubleWell),
+    Morse(Morse),
+    PoschlTeller(PoschlTeller),
+    KronigPenney(KronigPenney),
+    Step(Step),
+    Expression {
+        #[serde(deserialize_with = "static_expression")]
+        value: Expression,
+    },
+}
+impl Builtin {
+    fn potential(&self) -> &dyn Potential {
+        match self {
+            Self::Barrier(p) => p,
+            Self::Well(p) => p,
+            Self::Harmonic(p) => p,
+            Self::DoubleWell(p) => p,
+            Self::Morse(p) => p,
+            Self::PoschlTeller(p) => p,
+            Self::KronigPenney(p) => p,
+            Self::Step(p) => p,
+            Self::Expression { value } => value,
+        }
+    }
+}
+// static terms are only sampled at the start, so a formula of t would quietly stay at t = 0
+fn static_expression<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Expression, D::Error> {
+    let source = String::deserialize(deserializer)?;
+    Expression::parse_static(&source).map_err(serde::de::Error::custom)
+}
+
+impl Potential for Builtin {
+    fn value(&self, x: f64) -> f64 {
+        self.potential().value(x)
+    }
+
+    fn value_z(&self, z: f64) -> f64 {
+        self.potential().value_z(z)
+    }
+
+    fn value_2d(&self, x: f64, z: f64) -> f64 {
+        self.potential().value_2d(x, z)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+pub enum DrivingTerm {
+    MovingBarrier(MovingBarrier),
+    OscillatingWell(OscillatingWell),
+    ElectricField(ElectricField),
+    Kick(Kick),
+    Expression { value: Expression },
+}
+impl TimeDependent for DrivingTerm {
+    fn value_at(&self, x: f64, t: f64) -> f64 {
+        match self {
+            Self::MovingBarrier(p) => p.value_at(x, t),
+            Self::OscillatingWell(p) => p.value_at(x, t),
+            Self::ElectricField(p) => p.value_at(x, t),
+            Self::Kick(p) => p.value_at(x, t),
+            Self::Expression { value } => value.value_at(x, t),
+        }
+    }
+
+    fn value_at_2d(&self, x: f64, z: f64, t: f64) -> f64 {
+        match self {
+            Self::Expression { value } => value.value_at_2d(x, z, t),
+            _ => self.value_at(x, t),
+        }
+    }
+}
+
+// only the time independent terms, zero if the potential is disabled
+impl Potential for PotentialSpec {
+    fn value(&self, x: f64) -> f64 {
+        if self.enabled {
+            self.terms.iter().map(|term| term.value(x)).sum()
+        } else {
+            0.
+        }
+    }
+
+    fn value_z(&self, z: f64) -> f64 {
+        if self.enabled {
+            self.terms.iter().map(|term| term.value_z(z)).sum()
+        } else {
+            0.
+        }
+    }
+
+    fn value_2d(&self, x: f64, z: f64) -> f64 {
+        if self.enabled {
+            self.terms.iter().map(|term| term.value_2d(x, z)).sum()
+        } else {
+            0.
+        }
+    }
+}
+
+// only the driving terms, zero if the potential is disabled
+impl TimeDependent for PotentialSpec {
+    fn value_at(&self, x: f64, t: f64) -> f64 {
+        if self.enabled {
+            self.driving.iter().map(|term| term.value_at(x, t)).sum()
+        } else {
+            0.
+        }
+    }
+
+    fn value_at_2d(&self, x: f64, z: f64, t: f64) -> f64 {
+        if self.enabled {
+            self.driving
+                .iter()
+                .map(|term| term.value_at_2d(x, z, t))
+                .sum()
+        } else {
+            0.
+        }
+    }
+}
+
+impl PotentialSpec {
+    // Whether the 2D potential is V(x) + V_z(z), which every built in potential is. Formulas
+    // that depend on both x and y are taken to mix them, even if they are something like x + y.
+    pub fn is_separable(&self) -> bool {
+        !self.enabled
+            || self.terms.iter().all(|term| match &term.builtin {
+                Builtin::Expression { value } => {
+                    !(value.depends_on(Variable::X) && value.depends_on(Variable::Y))
+                }
+                _ => true,
+            })
+    }
+}
+
+// By default the wave function is zero just outside of the grid, which makes packets reflect
+// off the edges. With periodic boundaries the first and last grid points are neighbours
+// instead, so the period is the number of grid points times the spacing. An absorbing layer
+// damps everything that reaches the edges, and works with either choice.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Boundary {
+    pub periodic: bool,
+    pub absorbing: Option<AbsorbingLayer>,
+}
+
+// A complex absorbing potential -i*W(x) along the edges of the grid, where W grows
+// quadratically from zero at the inner side of the layer to strength at the edge.
+// Wider and weaker layers reflect less, but leave less room for the simulation itself.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AbsorbingLayer {
+    pub width: f64,
+    pub strength: f64,
+}
+impl AbsorbingLayer {
+    // W(x) for a grid of the given length
+    pub fn value(&self, x: f64, length: f64) -> f64 {
+        let depth = (x.abs() - (length / 2. - self.width)) / self.width;
+        if depth > 0. {
+            self.strength * depth.powi(2)
+        } else {
+            0.
+        }
+    }
+}
+
+impl Scenario {
+    // whether the Hamiltonian has any potential part at all
+    pub fn has_potential(&self) -> bool {
+        self.potential.enabled || self.boundary.absorbing.is_some()
+    }
+
+    // whether the potential changes over time
+    pub fn is_driven(&self) -> bool {
+        self.potential.enabled && !self.potential.driving.is_empty()
+    }
+
+    // Replaces the potential with the given formula, which becomes a driving term if it
+    // depends on time
+    pub fn set_potential(&mut self, expression: Expression) {
+        self.potential.enabled = true;
+        if expression.depends_on(Variable::T) {
+            self.potential.terms = Vec::new();
+            self.potential.driving = vec![DrivingTerm::Expression { value: expression }];
+        } else {
+            self.potential.terms = vec![Builtin::Expression { value: expression }.into()];
+            self.potential.driving = Vec::new();
+        }
+    }
+
+    // the defaults for the given number of dimensions, two dimensions use a coarser grid
+    pub fn default_for(dims: u8) -> Self {
+        if dims == 2 {
+            Self {
+                grid: Grid {
+                    length: 8.,
+                    spacing: 0.1,
+                },
+                time: Time {
+                    dt: 0.001,
+                    integrator: Integrator::RungeKutta4,
+                },
+                initial: InitialState {
+                    k_range: 5,
+                    ..Self::default().initial
+                },
+                potential: PotentialSpec {
+                    enabled: false,
+                    terms: vec![Builtin::Barrier(Barrier {
+                        start: 2.5,
+                        end: 3.,
+                        height: 1.,
+                    })
+                    .into()],
+                    driving: Vec::new(),
+                },
+                ..Self::default()
+            }
+        } else {
+            Self::default()
+        }
+    }
+
+    // Reads a scenario from a toml file. Values missing from the file are taken from
+    // the defaults for the given number of dimensions.
+    pub fn load(path: impl AsRef<Path>, dims: u8) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let content = fs::read_to_string(path).map_err(|e| {
+            Error::new(
+                e.kind(),
+                format!("Failed to read scenario file {}: {e}", path.display()),
+            )
+        })?;
+        Self::parse(&content, dims).map_err(|e| {
+            Error::new(
+                ErrorKind::InvalidData,
+                format!("Invalid scenario file {}: {e}", path.display()),
+            )
+        })
+    }
+
+    pub fn parse(content: &str, dims: u8) -> Result<Self, Error> {
+        let invalid = |e: toml::de::Error| Error::new(ErrorKind::InvalidData, e.to_string());
+        let mut scenario = toml::Value::try_from(Self::default_for(dims))
+            .expect("The default scenario is always representable as toml");
+        merge(
+            &mut scenario,
+            content.parse::<toml::Value>().map_err(invalid)?,
+        );
+        let scenario: Self = scenario.try_into().map_err(invalid)?;
+        scenario.check()?;
+        Ok(scenario)
+    }
+
+    // rejects the values the grid and the solvers can't work with
+    fn check(&self) -> Result<(), Error> {
+        let positive = [
+            ("constants.m", self.constants.m),
+            ("grid.length", self.grid.length),
+            ("grid.spacing", self.grid.spacing),
+            ("time.dt", self.time.dt),
+        ];
+        for (name, value) in positive {
+            if !value.is_finite() || value <= 0. {
+                return Err(Error::new(
+                    ErrorKind::InvalidData,
+                    format!("{name} has to be a positive number, got {value}"),
+                ));
+            }
+        }
+        if self.grid.half() == 0 {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!(
+                    "The grid needs at least 3 points, so grid.length has to be at least \
+                     twice grid.spacing, got {} and {}",
+                    self.grid.length, self.grid.spacing
+                ),
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl Default for Scenario {
+    fn default() -> Self {
+        Self {
+            constants: Constants { h: 1., m: 1. },
+            grid: Grid {
+                length: 8.,
+                spacing: 0.01,
+            },
+            time: Time {
+                dt: 0.0005,
+                integrator: Integrator::RungeKutta4,
+            },
+            initial: InitialState {
+                k_0: 10.,
+                k_range: 10,
+                dk: 0.5,
+                delta_k: 5.,
+                components: Vec::new(),
+            },
+            potential: PotentialSpec {
+                enabled: false,
+                terms: vec![
+                    Builtin::Barrier(Barrier {
+                        start: 2.5,
+                        end: 3.,
+                        height: 1.,
+                    })
+                    .into(),
+                    Builtin::Harmonic(Harmonic { strength: 1. }).into(),
+                ],
+                driving: Vec::new(),
+            },
+            boundary: Boundary::default(),
+        }
+    }
+}
+
+// overwrites the values in base with the ones in update, tables are merged key by key
+fn merge(base: &mut toml::Value, update: toml::Value) {
+    match (base, update) {
+        (toml::Value::Table(base), toml::Value::Table(update)) => {
+            for (key, value) in update {
+                match base.get_mut(&key) {
+                    Some(existing) => merge(existing, value),
+                    None => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        (base, update) => *base = update,
+    }
+}
@@ -0,0 +1,380 @@
+use std::io::{Error, ErrorKind};
+
+use nalgebra::{DMatrix, DVector};
+
+use crate::{
+    checkpoint::Checkpoint,
+    complex::{Complex, Real},
+    initial::WaveBuilder,
+    one_dim,
+    scenario::{Grid, Integrator, Scenario},
+    two_dim,
+    utils::Rk4Workspace,
+};
+
+// psi sampled at the points of the grid, in one or two dimensions.
+// In two dimensions the rows run along x and the columns along z.
+// Everything here is f64 by default, or f32 for speed when accuracy matters less.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WaveFunction<T: Real = f64>(Values<T>);
+
+// the Hamiltonian of a scenario, with its potential sampled once on the grid
+#[derive(Debug, Clone)]
+pub struct Hamiltonian<T: Real = f64> {
+    scenario: Scenario,
+    potential: Values<T>,
+}
+
+// Steps a wave function forward by the dt of the scenario. Two dimensions always use RK4,
+// no matter the integrator.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Propagator {
+    integrator: Integrator,
+}
+
+// A scenario being run, i.e. its Hamiltonian, the propagator and the current wave function
+#[derive(Debug, Clone)]
+pub struct Simulation<T: Real = f64> {
+    hamiltonian: Hamiltonian<T>,
+    propagator: Propagator,
+    psi: WaveFunction<T>,
+    step: usize,
+    workspace: Rk4Workspace<T>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Values<T: Real> {
+    OneD(DVector<Complex<T>>),
+    TwoD(DMatrix<Complex<T>>),
+}
+impl<T: Real> Values<T> {
+    fn dims(&self) -> u8 {
+        match self {
+            Values::OneD(_) => 1,
+            Values::TwoD(_) => 2,
+        }
+    }
+
+    fn as_slice(&self) -> &[Complex<T>] {
+        match self {
+            Values::OneD(values) => values.as_slice(),
+            Values::TwoD(values) => values.as_slice(),
+        }
+    }
+}
+
+fn check_dims(dims: u8) -> Result<(), Error> {
+    if dims == 1 || dims == 2 {
+        Ok(())
+    } else {
+        Err(Error::new(
+            ErrorKind::InvalidInput,
+            format!("Only one and two dimensions are supported, got {dims}"),
+        ))
+    }
+}
+
+fn mismatch<T: Real>(hamiltonian: &Hamiltonian<T>, psi: &WaveFunction<T>) -> Error {
+    Error::new(
+        ErrorKind::InvalidInput,
+        format!(
+            "The Hamiltonian is in {} dimensions, but the wave function in {}",
+            hamiltonian.dims(),
+            psi.dims()
+        ),
+    )
+}
+
+// a simulation builds its wave function and its Hamiltonian for the same number of dimensions
+const MATCHING: &str = "The wave function of a simulation has the dimensions of its Hamiltonian";
+
+impl<T: Real> WaveFunction<T> {
+    // the initial state described by the scenario
+    pub fn initial(s: &Scenario, dims: u8) -> Result<Self, Error> {
+        check_dims(dims)?;
+        let builder = WaveBuilder::from_scenario(s);
+        Ok(Self(if dims == 1 {
+            Values::OneD(builder.build_1d(s)?.map(Complex::cast))
+        } else {
+            Values::TwoD(builder.build_2d(s)?.map(Complex::cast))
+        }))
+    }
+
+    pub fn from_1d(values: DVector<Complex<T>>) -> Self {
+        Self(Values::OneD(values))
+    }
+
+    pub fn from_2d(values: DMatrix<Complex<T>>) -> Self {
+        Self(Values::TwoD(values))
+    }
+
+    pub fn dims(&self) -> u8 {
+        self.0.dims()
+    }
+
+    pub fn as_1d(&self) -> Option<&DVector<Complex<T>>> {
+        match &self.0 {
+            Values::OneD(values) => Some(values),
+            Values::TwoD(_) => None,
+        }
+    }
+
+    pub fn as_2d(&self) -> Option<&DMatrix<Complex<T>>> {
+        match &self.0 {
+            Values::OneD(_) => None,
+            Values::TwoD(values) => Some(values),
+        }
+    }
+
+    // the number of values along each axis
+    pub fn shape(&self) -> (usize, usize) {
+        match &self.0 {
+            Values::OneD(values) => values.shape(),
+            Values::TwoD(values) => values.shape(),
+        }
+    }
+
+    // the same wave function in another precision
+    pub fn cast<U: Real>(&self) -> WaveFunction<U> {
+        WaveFunction(match &self.0 {
+            Values::OneD(values) => Values::OneD(values.map(Complex::cast)),
+            Values::TwoD(values) => Values::TwoD(values.map(Complex::cast)),
+        })
+    }
+
+    // int{|psi|^2}, taken as a plain sum over the grid and always added up in f64
+    pub fn norm(&self, grid: &Grid) -> f64 {
+        self.0
+            .as_slice()
+            .iter()
+            .map(|p| p.abs_squared().as_f64())
+            .sum::<f64>()
+            * grid.spacing.powi(self.dims() as i32)
+    }
+}
+
+impl<T: Real> Hamiltonian<T> {
+    pub fn new(scenario: &Scenario, dims: u8) -> Result<Self, Error> {
+        check_dims(dims)?;
+        let potential = if dims == 1 {
+            Values::OneD(one_dim::iteration::potential_vector(scenario).map(Complex::cast))
+        } else {
+            Values::TwoD(two_dim::iteration::potential_matrix(scenario).map(Complex::cast))
+        };
+        Ok(Self {
+            scenario: scenario.clone(),
+            potential,
+        })
+    }
+
+    pub fn scenario(&self) -> &Scenario {
+        &self.scenario
+    }
+
+    pub fn grid(&self) -> &Grid {
+        &self.scenario.grid
+    }
+
+    pub fn dims(&self) -> u8 {
+        self.potential.dims()
+    }
+
+    // H psi at time t, with the same finite differences the propagators use.
+    // Fails if psi doesn't have the dimensions of the Hamiltonian.
+    pub fn apply(&self, psi: &WaveFunction<T>, t: f64) -> Result<WaveFunction<T>, Error> {
+        let s = &self.scenario;
+        Ok(WaveFunction(match (&self.potential, &psi.0) {
+            (Values::OneD(v), Values::OneD(f)) => {
+                let v = one_dim::iteration::potential_at(v, t, s);
+                Values::OneD(one_dim::iteration::hamiltonian(f, &v, s))
+            }
+            (Values::TwoD(v), Values::TwoD(f)) => {
+                let v = two_dim::iteration::potential_at(v, t, s);
+                Values::TwoD(two_dim::iteration::hamiltonian(f, &v, s))
+            }
+            _ => return Err(mismatch(self, psi)),
+        }))
+    }
+
+    // <psi|H|psi> / <psi|psi> at time t
+    pub fn energy(&self, psi: &WaveFunction<T>, t: f64) -> Result<f64, Error> {
+        let h_psi = self.apply(psi, t)?;
+        let mut expectation = Complex::from_real(0.);
+        let mut norm = 0.;
+        for (p, h_p) in psi.0.as_slice().iter().zip(h_psi.0.as_slice()) {
+            expectation += (p.complex_conjugate() * *h_p).cast::<f64>();
+            norm += p.abs_squared().as_f64();
+        }
+        Ok(expectation.real() / norm)
+    }
+}
+
+impl Propagator {
+    pub fn new(integrator: Integrator) -> Self {
+        Self { integrator }
+    }
+
+    // uses the integrator chosen in the scenario
+    pub fn from_scenario(s: &Scenario) -> Self {
+        Self::new(s.time.integrator)
+    }
+
+    pub fn integrator(&self) -> Integrator {
+        self.integrator
+    }
+
+    // psi at t + dt from psi at t.
+    // Fails if psi doesn't have the dimensions of the Hamiltonian.
+    pub fn step<T: Real>(
+        &self,
+        hamiltonian: &Hamiltonian<T>,
+        psi: &WaveFunction<T>,
+        t: f64,
+    ) -> Result<WaveFunction<T>, Error> {
+        let mut res = psi.clone();
+        self.step_in_place(hamiltonian, &mut res, t, &mut Rk4Workspace::default())?;
+        Ok(res)
+    }
+
+    // step for psi itself, where RK4 keeps its buffers in the workspace
+    fn step_in_place<T: Real>(
+        &self,
+        hamiltonian: &Hamiltonian<T>,
+        psi: &mut WaveFunction<T>,
+        t: f64,
+        workspace: &mut Rk4Workspace<T>,
+    ) -> Result<(), Error> {
+        let s = &hamiltonian.scenario;
+        match (&hamiltonian.potential, &mut psi.0) {
+            (Values::OneD(v), Values::OneD(f)) => match self.integrator {
+                Integrator::RungeKutta4 => one_dim::iteration::rk4_step(f, v, t, s, workspace),
+                Integrator::CrankNicolson => *f = one_dim::iteration::cn_iter_dt(f, v, t, s),
+                Integrator::SplitOperator => {
+                    *f = one_dim::iteration::split_operator_iter_dt(f, v, t, s)
+                }
+            },
+            (Values::TwoD(v), Values::TwoD(f)) => {
+                two_dim::iteration::rk4_step(f, v, t, s, workspace)
+            }
+            _ => return Err(mismatch(hamiltonian, psi)),
+        }
+        Ok(())
+    }
+}
+
+impl<T: Real> Simulation<T> {
+    // starts from the initial state of the scenario
+    pub fn new(scenario: Scenario, dims: u8) -> Result<Self, Error> {
+        Ok(Self {
+            psi: WaveFunction::initial(&scenario, dims)?,
+            hamiltonian: Hamiltonian::new(&scenario, dims)?,
+            propagator: Propagator::from_scenario(&scenario),
+            step: 0,
+            workspace: Rk4Workspace::default(),
+        })
+    }
+
+    // picks up at the step the checkpoint was saved at, without building the initial state
+    pub fn from_checkpoint(checkpoint: &Checkpoint) -> Result<Self, Error> {
+        let s = &checkpoint.scenario;
+        let hamiltonian = Hamiltonian::new(s, checkpoint.dims)?;
+        let psi = if checkpoint.dims == 1 {
+            WaveFunction::from_1d(checkpoint.psi_1d())
+        } else {
+            WaveFunction::from_2d(checkpoint.psi_2d())
+        };
+        Ok(Self {
+            hamiltonian,
+            propagator: Propagator::from_scenario(s),
+            psi: psi.cast(),
+            step: checkpoint.step,
+            workspace: Rk4Workspace::default(),
+        })
+    }
+
+    pub fn scenario(&self) -> &Scenario {
+        &self.hamiltonian.scenario
+    }
+
+    pub fn grid(&self) -> &Grid {
+        self.hamiltonian.grid()
+    }
+
+    pub fn dims(&self) -> u8 {
+        self.hamiltonian.dims()
+    }
+
+    pub fn hamiltonian(&self) -> &Hamiltonian<T> {
+        &self.hamiltonian
+    }
+
+    pub fn propagator(&self) -> Propagator {
+        self.propagator
+    }
+
+    pub fn set_propagator(&mut self, propagator: Propagator) {
+        self.propagator = propagator;
+    }
+
+    pub fn wave_function(&self) -> &WaveFunction<T> {
+        &self.psi
+    }
+
+    // replaces the current wave function, which has to fit the grid
+    pub fn set_wave_function(&mut self, psi: WaveFunction<T>) -> Result<(), Error> {
+        if psi.dims() != self.psi.dims() || psi.shape() != self.psi.shape() {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!(
+                    "The wave function has {}x{} values, which doesn't fit the grid",
+                    psi.shape().0,
+                    psi.shape().1
+                ),
+            ));
+        }
+        self.psi = psi;
+        Ok(())
+    }
+
+    // the number of steps taken so far
+    pub fn step(&self) -> usize {
+        self.step
+    }
+
+    pub fn time(&self) -> f64 {
+        self.step as f64 * self.scenario().time.dt
+    }
+
+    pub fn advance(&mut self) {
+        let t = self.time();
+        self.propagator
+            .step_in_place(&self.hamiltonian, &mut self.psi, t, &mut self.workspace)
+            .expect(MATCHING);
+        self.step += 1;
+    }
+
+    pub fn run(&mut self, steps: usize) {
+        for _ in 0..steps {
+            self.advance();
+        }
+    }
+
+    pub fn norm(&self) -> f64 {
+        self.psi.norm(self.grid())
+    }
+
+    pub fn energy(&self) -> f64 {
+        self.hamiltonian
+            .energy(&self.psi, self.time())
+            .expect(MATCHING)
+    }
+
+    // checkpoints are always stored in f64
+    pub fn checkpoint(&self) -> Checkpoint {
+        let (step, time, s) = (self.step, self.time(), self.scenario());
+        match &self.psi.cast::<f64>().0 {
+            Values::OneD(psi) => Checkpoint::from_1d(step, time, s, psi),
+            Values::TwoD(psi) => Checkpoint::from_2d(step, time, s, psi),
+        }
+    }
+}
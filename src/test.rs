@@ -0,0 +1,640 @@
+use std::{f64::consts::PI, io::ErrorKind, path::PathBuf};
+
+use nalgebra::DVector;
+use num_traits::{Num, One, Zero};
+
+use crate::{
+    checkpoint::Checkpoint,
+    complex::*,
+    export::{Exporter, Frame},
+    expression::{Expression, Variable},
+    fft::{fft, ifft},
+    initial::WaveBuilder,
+    one_dim::iteration::potential_vector,
+    potential::{
+        Barrier, DoubleWell, Harmonic, PoschlTeller, Potential, Step, TimeDependent, Well,
+    },
+    quadrature::{gauss_legendre, legendre_nodes, romberg, simpson, simpson_3_8, trapezoid},
+    scenario::{AbsorbingLayer, Builtin, Integrator, Scenario},
+    utils::solve_cyclic_tridiagonal,
+    Simulation,
+};
+
+// Textbook RK4 with every stage stored, where h(f, t) is H f at time t. The fused stages of
+// Rk4Workspace are checked against this in one and two dimensions.
+pub fn rk4_reference(
+    psi: &[Complex],
+    t: f64,
+    dt: f64,
+    h_bar: f64,
+    h: impl Fn(&[Complex], f64) -> Vec<Complex>,
+) -> Vec<Complex> {
+    let k = |f: &[Complex], t: f64| {
+        h(f, t)
+            .into_iter()
+            .map(|h_f| h_f * Complex::new(0., -dt / h_bar))
+            .collect::<Vec<Complex>>()
+    };
+    let ahead = |k: &[Complex], by: f64| {
+        psi.iter()
+            .zip(k)
+            .map(|(p, k)| *p + *k * by)
+            .collect::<Vec<Complex>>()
+    };
+    let k1 = k(psi, t);
+    let k2 = k(&ahead(&k1, 0.5), t + dt / 2.);
+    let k3 = k(&ahead(&k2, 0.5), t + dt / 2.);
+    let k4 = k(&ahead(&k3, 1.), t + dt);
+    (0..psi.len())
+        .map(|j| psi[j] + (k1[j] + (k2[j] + k3[j]) * 2. + k4[j]) / 6.)
+        .collect()
+}
+
+// a file in the temporary directory that no other test, or other run of the tests, uses
+fn temp_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("quantum_playground_{}_{name}", std::process::id()))
+}
+
+#[test]
+fn basic_complex_arithmetic() {
+    //addition (2-5i)+(-4+9i)
+    assert_eq!((2. - 5. * i()) + (-4. + 9. * i()), -2. + 4. * i());
+    assert_eq!((2. - 5. * i()) - (-4. + 9. * i()), 6. - 14. * i());
+    assert_eq!((2. - 5. * i()) * (-4. + 9. * i()), 37. + 38. * i());
+    assert_eq!(
+        (2. - 5. * i()) / (-4. + 9. * i()),
+        -53. / 97. + 2. / 97. * i()
+    );
+
+    assert_eq!(i() * i(), Complex::new(-1., 0.));
+}
+
+#[test]
+fn f64_complex_arithmetic() {
+    assert_eq!((2. - 5. * i()), Complex::new(2., -5.));
+    assert_eq!((-4. + 9. * i()), Complex::new(-4., 9.));
+    assert_eq!(3. * (1. - 2. * i()), Complex::new(3., -6.));
+    assert_eq!(1. / (4. + 6. * i()), Complex::new(1. / 13., -3. / 26.));
+}
+
+#[test]
+fn f32_complex_arithmetic() {
+    let z: Complex<f32> = Complex::new(1.5, -0.5);
+    assert_eq!(z * z, Complex::new(2., -1.5));
+    assert_eq!(2f32 * z - Complex::<f32>::i(), Complex::new(3., -2.));
+    assert_eq!(Complex::<f64>::from(z), 1.5 - 0.5 * i());
+    assert_eq!((1.5 - 0.5 * i()).cast::<f32>(), z);
+    assert_eq!(
+        z * DVector::from(vec![Complex::one(), z]),
+        DVector::from(vec![z, Complex::new(2., -1.5)])
+    );
+}
+
+#[test]
+fn complex_display() {
+    assert_eq!(format!("{}", 5. + 2. * i()), "5+2i");
+    assert_eq!(format!("{}", 5. - 2. * i()), "5-2i");
+}
+
+#[test]
+fn exponential() {
+    assert!((Complex::exp(0. - i() * PI) - Complex::new(-1., 0.)).abs_squared() < 1e-30);
+}
+
+#[test]
+fn assign() {
+    let mut a = 2. - 5. * i();
+    a += -4. + 9. * i();
+    assert_eq!(a, -2. + 4. * i());
+
+    let mut s = 2. - 5. * i();
+    s -= -4. + 9. * i();
+    assert_eq!(s, 6. - 14. * i());
+
+    let mut m = 2. - 5. * i();
+    m *= -4. + 9. * i();
+    assert_eq!(m, 37. + 38. * i());
+
+    let mut d = 2. - 5. * i();
+    d /= -4. + 9. * i();
+    assert_eq!(d, -53. / 97. + 2. / 97. * i());
+}
+
+#[test]
+fn complex_functions() {
+    let close = |a: Complex, b: Complex| (a - b).abs() < 1e-12;
+    let z = 1.5 - 0.5 * i();
+    assert_eq!(-z, Complex::new(-1.5, 0.5));
+    assert_eq!(Complex::new(3., 4.).abs(), 5.);
+    assert_eq!(i().arg(), PI / 2.);
+    assert!(close(Complex::from_polar(2., PI / 2.), 2. * i()));
+    let (r, theta) = z.to_polar();
+    assert!(close(Complex::from_polar(r, theta), z));
+
+    // roots, logs and powers
+    assert_eq!(Complex::from_real(-4.).sqrt(), 2. * i());
+    assert!(close(z.sqrt() * z.sqrt(), z));
+    assert!(close(z.cbrt().powi(3), z));
+    assert!(close(z.ln().exp(), z));
+    assert!(close(Complex::from_real(-1.).ln(), PI * i()));
+    assert!(close(
+        Complex::from_real(100.).log10(),
+        Complex::from_real(2.)
+    ));
+    assert_eq!(z.powi(3), z * z * z);
+    assert!(close(z.powi(-2) * z * z, Complex::one()));
+    assert!(close(z.powf(0.5), z.sqrt()));
+    assert!(close(i().powc(i()), Complex::from_real((-PI / 2.).exp())));
+    let zero: Complex = Complex::zero();
+    assert_eq!(zero.powc(zero), Complex::one());
+
+    // the inverse functions undo the functions on their principal branch, which z is on
+    type Function = fn(Complex) -> Complex;
+    let functions: [(Function, Function); 6] = [
+        (Complex::sin, Complex::asin),
+        (Complex::cos, Complex::acos),
+        (Complex::tan, Complex::atan),
+        (Complex::sinh, Complex::asinh),
+        (Complex::cosh, Complex::acosh),
+        (Complex::tanh, Complex::atanh),
+    ];
+    for (f, inverse) in functions {
+        assert!(close(inverse(f(z)), z));
+    }
+    assert!(close(z.sin().powi(2) + z.cos().powi(2), Complex::one()));
+    assert!(close(z.cosh().powi(2) - z.sinh().powi(2), Complex::one()));
+    assert!(close(z.tan(), z.sin() / z.cos()));
+    assert!(close(z.tanh(), z.sinh() / z.cosh()));
+    // no overflow far away from the real axis
+    assert!(close((1. + 400. * i()).tan(), i()));
+    assert!(close((-400. + i()).tanh(), -Complex::one()));
+
+    assert_eq!([z, i(), 2. * i()].iter().sum::<Complex>(), z + 3. * i());
+    assert_eq!(
+        [z, i(), 2. * i()].into_iter().product::<Complex>(),
+        z * i() * 2. * i()
+    );
+    assert_eq!((7. + 3. * i()) % (2. + i()), Complex::from_real(1.));
+}
+
+#[test]
+fn complex_parsing() {
+    let parse = |s: &str| s.parse::<Complex>();
+    assert_eq!(parse("3"), Ok(Complex::from_real(3.)));
+    assert_eq!(parse("i"), Ok(i()));
+    assert_eq!(parse("-i"), Ok(-i()));
+    assert_eq!(parse("-2.5i"), Ok(Complex::new(0., -2.5)));
+    assert_eq!(parse(" 3 + 4i "), Ok(Complex::new(3., 4.)));
+    assert_eq!(parse("1e-3-2E+2i"), Ok(Complex::new(1e-3, -200.)));
+    assert_eq!(parse("2e-3i"), Ok(Complex::new(0., 2e-3)));
+    assert_eq!(parse("1-infi"), Ok(Complex::new(1., f64::NEG_INFINITY)));
+    assert!(parse("NaN+NaNi").unwrap().is_nan());
+    for invalid in ["", "1+2", "abc", "1+2j", "1++2i"] {
+        assert!(parse(invalid).is_err());
+    }
+    assert_eq!(
+        Complex::from_str_radix("ff-10i", 16),
+        Ok(Complex::new(255., -16.))
+    );
+
+    // whatever is displayed parses back into the same number
+    for z in [
+        5. + 2. * i(),
+        Complex::new(-0.1, -1e-7),
+        Complex::new(1e300, f64::INFINITY),
+        Complex::new(0., -0.),
+        Complex::new(1. / 3., 0.),
+    ] {
+        let parsed = parse(&z.to_string()).unwrap();
+        assert_eq!(parsed, z);
+        assert_eq!(
+            parsed.imag().is_sign_negative(),
+            z.imag().is_sign_negative()
+        );
+    }
+    assert_eq!(format!("{:.2}", 1. / 3. - 2. / 3. * i()), "0.33-0.67i");
+}
+
+// iteration testing
+
+#[test]
+fn fft_matches_dft() {
+    // powers of two, mixed factors and a prime length
+    for n in [8, 12, 45, 13] {
+        let data = DVector::from(
+            (0..n)
+                .map(|j| Complex::new((j as f64).sin(), (j as f64 * 0.3).cos()))
+                .collect::<Vec<Complex>>(),
+        );
+        let transformed = fft(&data);
+        for k in 0..n {
+            let mut dft = Complex::zero();
+            for j in 0..n {
+                dft += data[j] * Complex::exp(i() * (-2. * PI * (j * k) as f64 / n as f64));
+            }
+            assert!((transformed[k] - dft).abs_squared() < 1e-20);
+        }
+
+        let back = ifft(&transformed);
+        for j in 0..n {
+            assert!((back[j] - data[j]).abs_squared() < 1e-24);
+        }
+    }
+}
+
+#[test]
+fn scenario_parsing() {
+    let s = Scenario::parse(
+        r#"
+        [grid]
+        spacing = 0.02
+
+        [time]
+        integrator = "split-operator"
+
+        [potential]
+        enabled = true
+        terms = [{ type = "harmonic", strength = 0.5 }]
+
+        [boundary]
+        absorbing = { width = 1.0, strength = 5.0 }
+        "#,
+        1,
+    )
+    .unwrap();
+
+    // values in the file replace the defaults, everything else is left as is
+    assert_eq!(s.grid.spacing, 0.02);
+    assert_eq!(s.grid.length, Scenario::default().grid.length);
+    assert_eq!(s.time.integrator, Integrator::SplitOperator);
+    assert_eq!(s.time.dt, Scenario::default().time.dt);
+    assert_eq!(
+        s.potential.terms,
+        vec![Builtin::Harmonic(Harmonic { strength: 0.5 }).into()]
+    );
+    assert_eq!(
+        s.boundary.absorbing,
+        Some(AbsorbingLayer {
+            width: 1.,
+            strength: 5.
+        })
+    );
+    assert_eq!(Scenario::parse("", 1).unwrap(), Scenario::default());
+
+    assert!(Scenario::parse("[time]\nintegrator = \"euler\"", 1).is_err());
+}
+
+#[test]
+fn scenario_validation() {
+    let error = |content: &str| Scenario::parse(content, 1).unwrap_err();
+    for (table, key) in [
+        ("constants", "m"),
+        ("grid", "length"),
+        ("grid", "spacing"),
+        ("time", "dt"),
+    ] {
+        for value in ["0.0", "-1.0", "nan", "inf"] {
+            let e = error(&format!("[{table}]\n{key} = {value}"));
+            assert_eq!(e.kind(), ErrorKind::InvalidData);
+            assert!(e.to_string().starts_with(&format!("{table}.{key} ")), "{e}");
+        }
+    }
+
+    // two points of spacing 0.5 don't make a grid
+    let e = error("[grid]\nlength = 0.9\nspacing = 0.5");
+    assert!(e.to_string().contains("at least 3 points"), "{e}");
+    assert!(Scenario::parse("[grid]\nlength = 1.0\nspacing = 0.5", 1).is_ok());
+}
+
+#[test]
+fn potential_composition() {
+    let barrier = Barrier {
+        start: -0.5,
+        end: 0.5,
+        height: 3.,
+    };
+    let v = Harmonic { strength: 2. } + barrier.scaled(2.).shifted(1.);
+    assert_eq!(v.value(1.), 2. + 6.);
+    assert_eq!(v.value(0.), 0.);
+    // only the harmonic part depends on z
+    assert_eq!(v.value_2d(1., 2.), 8. + 8.);
+
+    let v = Well {
+        start: 0.,
+        end: 1.,
+        depth: 2.,
+    } + Step {
+        position: 0.5,
+        height: 1.,
+    } + DoubleWell {
+        separation: 2.,
+        depth: 1.,
+    } + PoschlTeller {
+        depth: 1.,
+        width: 1.,
+    };
+    // inside the well, right of the step, between the minima of the double well
+    let expected = -2. + 1. + (0.75f64.powi(2) - 1.).powi(2) - 1. / 0.75f64.cosh().powi(2);
+    assert!((v.value(0.75) - expected).abs() < 1e-14);
+
+    // terms from a scenario file can be scaled and shifted as well
+    let mut s = Scenario::parse(
+        r#"
+        [potential]
+        enabled = true
+        terms = [
+            { type = "morse", depth = 2.0, a = 1.5, center = 0.0, shift = 1.0 },
+            { type = "kronig-penney", period = 1.0, width = 0.2, height = 4.0, scale = 0.5 },
+        ]
+        "#,
+        1,
+    )
+    .unwrap();
+    assert_eq!(s.potential.value(1.), 2.);
+    assert!((s.potential.value(1.5) - 2. * (1. - (-0.75f64).exp()).powi(2)).abs() < 1e-14);
+    let potential = potential_vector(&s);
+    for (x, v) in s.grid.points().iter().zip(potential.iter()) {
+        assert_eq!(v.real(), s.potential.value(*x));
+    }
+    s.potential.enabled = false;
+    assert_eq!(s.potential.value(1.), 0.);
+}
+
+#[test]
+fn expression_parser() {
+    let eval = |source: &str, x: f64| Expression::parse(source).unwrap().eval(x, 0., 0.);
+    assert_eq!(eval("1 + 2*3 - 4/2", 0.), 5.);
+    // ^ binds stronger than the sign and groups to the right
+    assert_eq!(eval("-x^2", 2.), -4.);
+    assert_eq!(eval("2^3^2", 0.), 512.);
+    assert_eq!(eval("e^-x", 1.), 1. / std::f64::consts::E);
+    assert_eq!(eval("1.5e2 + 2.5E-1", 0.), 150.25);
+    assert_eq!(eval("max(x, 1) + if(x > 2, 10, 0) + (x <= 3)", 3.), 14.);
+    assert!((eval("0.5*x^2 + 3*exp(-(x-1)^2/0.1)", 1.) - 3.5).abs() < 1e-15);
+    let v = Expression::parse("x^2 + sin(pi*y/2)*t").unwrap();
+    assert_eq!(v.eval(2., 1., 3.), 7.);
+    assert!(v.depends_on(Variable::T) && !Expression::parse("x").unwrap().depends_on(Variable::T));
+
+    // the same barrier as the built in one
+    let barrier = Expression::parse("step(x-2.5)*step(3-x)").unwrap();
+    let builtin = Barrier {
+        start: 2.5,
+        end: 3.,
+        height: 1.,
+    };
+    let s = Scenario::default();
+    for x in s.grid.points() {
+        assert_eq!(barrier.value(x), builtin.value(x));
+    }
+
+    // errors point at the character that is wrong
+    let position = |source: &str| Expression::parse(source).unwrap_err().position();
+    assert_eq!(position("0.5*x^2 + $"), 10);
+    assert_eq!(position("x y"), 2);
+    assert_eq!(position("2*(x + 1"), 2);
+    assert_eq!(position("3 * foo(x)"), 4);
+    assert_eq!(position("min(x) + 1"), 0);
+    assert_eq!(position("x * z"), 4);
+    assert_eq!(position("1 +"), 3);
+    assert_eq!(position("1.2.3"), 0);
+    let message = Expression::parse("0.5*x^2 + $").unwrap_err().to_string();
+    assert!(message.starts_with("unexpected character '$' at position 11"));
+    assert!(message.ends_with("\n              ^"));
+
+    // in a scenario, formulas of t become driving terms
+    let s = Scenario::parse(
+        r#"
+        [potential]
+        enabled = true
+        terms = [{ type = "expression", value = "0.5*x^2", scale = 2.0 }]
+        driving = [{ type = "expression", value = "x*cos(t)" }]
+        "#,
+        1,
+    )
+    .unwrap();
+    assert_eq!(s.potential.value(2.), 4.);
+    assert_eq!(s.potential.value_at(2., PI), -2.);
+    assert!(Scenario::parse(
+        "[potential]\nterms = [{ type = \"expression\", value = \"x +\" }]",
+        1
+    )
+    .is_err());
+
+    // but not in the static terms, which are only sampled at t = 0
+    let e = Scenario::parse(
+        "[potential]\nterms = [{ type = \"expression\", value = \"x^2 + t\" }]",
+        1,
+    )
+    .unwrap_err();
+    assert!(
+        e.to_string()
+            .contains("t is only allowed in the driving terms"),
+        "{e}"
+    );
+    assert_eq!(
+        Expression::parse_static("x^2 + t").unwrap_err().position(),
+        6
+    );
+    assert!(Expression::parse_static("x^2 + tan(x)").is_ok());
+
+    let mut s = Scenario::default();
+    s.set_potential(Expression::parse("x*t").unwrap());
+    assert!(s.is_driven() && s.potential.terms.is_empty());
+}
+
+#[test]
+fn quadrature() {
+    let sample = |f: fn(f64) -> f64, n: usize, a: f64, b: f64| {
+        let dx = (b - a) / (n - 1) as f64;
+        (
+            (0..n).map(|j| f(a + j as f64 * dx)).collect::<Vec<f64>>(),
+            dx,
+        )
+    };
+
+    // the trapezoid rule is exact for lines and Simpson's rules for cubics, for any point count
+    let cubic = |x: f64| x.powi(3) - 2. * x + 1.;
+    for n in 2..12 {
+        let (data, dx) = sample(|x| 3. * x + 1., n, 0., 2.);
+        assert!((trapezoid(&data, dx) - 8.).abs() < 1e-12);
+        let (data, dx) = sample(cubic, n, -1., 2.);
+        // x^4/4 - x^2 + x from -1 to 2
+        if n > 2 {
+            assert!((simpson(&data, dx) - 3.75).abs() < 1e-12, "{n} points");
+        }
+        if n > 3 {
+            assert!((simpson_3_8(&data, dx) - 3.75).abs() < 1e-12, "{n} points");
+        }
+    }
+
+    // the error of sin from 0 to pi drops with dx^2 and dx^4
+    let order = |rule: fn(&[f64], f64) -> f64, n: usize| {
+        let error = |n| {
+            let (data, dx) = sample(f64::sin, n, 0., PI);
+            (rule(&data, dx) - 2.).abs()
+        };
+        (error(n) / error(2 * n - 1)).log2()
+    };
+    for n in [21, 22, 23] {
+        assert!((order(trapezoid, n) - 2.).abs() < 0.05);
+        assert!((order(simpson, n) - 4.).abs() < 0.1, "{n} points");
+        assert!((order(simpson_3_8, n) - 4.).abs() < 0.1, "{n} points");
+    }
+
+    // int{e^(-x^2)} over the real line is sqrt(pi), and int{1/(1+x^2)} from 0 to 1 is pi/4
+    let gaussian = |x: f64| (-x * x).exp();
+    assert!((romberg(gaussian, -10., 10., 1e-12) - PI.sqrt()).abs() < 1e-10);
+    assert!((romberg(|x| 1. / (1. + x * x), 0., 1., 1e-12) - PI / 4.).abs() < 1e-10);
+    assert!((gauss_legendre(gaussian, -10., 10., 60) - PI.sqrt()).abs() < 1e-12);
+    assert!((gauss_legendre(|x| 1. / (1. + x * x), 0., 1., 20) - PI / 4.).abs() < 1e-14);
+
+    // n points are exact up to degree 2n - 1, and the weights add up to the length of [-1, 1]
+    for n in 1..10 {
+        let nodes = legendre_nodes(n);
+        assert!((nodes.iter().map(|(_, w)| w).sum::<f64>() - 2.).abs() < 1e-13);
+        let degree = 2 * n as i32 - 1;
+        let exact = (2f64.powi(degree + 1) - (-1f64).powi(degree + 1)) / (degree + 1) as f64;
+        let res = gauss_legendre(|x| x.powi(degree), -1., 2., n);
+        assert!((res - exact).abs() < 1e-11 * exact.abs(), "{n} points");
+    }
+}
+
+#[test]
+fn cyclic_tridiagonal() {
+    let n = 7;
+    let sub = (0..n - 1)
+        .map(|j| Complex::new(1., j as f64))
+        .collect::<Vec<_>>();
+    let sup = (0..n - 1)
+        .map(|j| Complex::new(-0.5, 0.1 * j as f64))
+        .collect::<Vec<_>>();
+    let mut diag = (0..n)
+        .map(|j| Complex::new(6. + j as f64, 1.))
+        .collect::<Vec<_>>();
+    let (upper, lower) = (Complex::new(0.3, -1.), Complex::new(2., 0.5));
+    let rhs = (0..n)
+        .map(|j| Complex::new(j as f64, 1.))
+        .collect::<Vec<_>>();
+
+    // the first entry of the diagonal is where Sherman-Morrison splits off the corners
+    for first in [diag[0], Complex::zero()] {
+        diag[0] = first;
+        let x = solve_cyclic_tridiagonal(&sub, &diag, &sup, upper, lower, &rhs);
+        for j in 0..n {
+            let mut row = diag[j] * x[j];
+            row += if j > 0 {
+                sub[j - 1] * x[j - 1]
+            } else {
+                upper * x[n - 1]
+            };
+            row += if j < n - 1 {
+                sup[j] * x[j + 1]
+            } else {
+                lower * x[0]
+            };
+            assert!((row - rhs[j]).abs_squared() < 1e-20, "{first}");
+        }
+    }
+}
+
+#[test]
+fn export_formats() {
+    let s = Scenario::default();
+    let x = s.grid.points();
+    let psi = WaveBuilder::from_scenario(&s).build_1d(&s).unwrap();
+    let frame = Frame::from_1d(100, 0.05, &x, &psi);
+    let n = x.len();
+
+    // one line per grid point after the header, and the same values in the npy data
+    let path = Exporter::new(temp_path("export.csv"), None)
+        .unwrap()
+        .write(&frame)
+        .unwrap();
+    let csv = std::fs::read_to_string(&path).unwrap();
+    let lines = csv.lines().collect::<Vec<&str>>();
+    assert_eq!(lines.len(), n + 1);
+    assert_eq!(lines[0], "x,re,im,prob");
+    let row = lines[401]
+        .split(',')
+        .map(|v| v.parse::<f64>().unwrap())
+        .collect::<Vec<f64>>();
+    assert_eq!(
+        row,
+        vec![
+            x[400],
+            psi[400].real(),
+            psi[400].imag(),
+            psi[400].abs_squared()
+        ]
+    );
+
+    // with every set, the step is part of the file name
+    let path = Exporter::new(temp_path("export.npy"), Some(50))
+        .unwrap()
+        .write(&frame)
+        .unwrap();
+    assert_eq!(path, temp_path("export_000100.npy"));
+    let npy = std::fs::read(&path).unwrap();
+    let header_length = u16::from_le_bytes([npy[8], npy[9]]) as usize;
+    let header = std::str::from_utf8(&npy[10..10 + header_length]).unwrap();
+    assert!(header.contains(&format!("'shape': ({n}, 4, )")));
+    assert_eq!((10 + header_length) % 64, 0);
+    assert_eq!(npy.len(), 10 + header_length + n * 4 * 8);
+    let at = 10 + header_length + (400 * 4 + 3) * 8;
+    let prob = f64::from_le_bytes(npy[at..at + 8].try_into().unwrap());
+    assert_eq!(prob, psi[400].abs_squared());
+    std::fs::remove_file(temp_path("export.csv")).unwrap();
+    std::fs::remove_file(path).unwrap();
+
+    assert!(Exporter::new("frame.txt", None).is_err());
+    assert!(Exporter::new("frame.csv", Some(0)).is_err());
+}
+
+#[test]
+fn checkpoint_round_trip() {
+    let mut s = Scenario::default();
+    s.time.integrator = Integrator::CrankNicolson;
+    s.set_potential(Expression::parse("2 * exp(-x^2) + 0.1 * sin(t)").unwrap());
+    let mut simulation = Simulation::new(s, 1).unwrap();
+    simulation.run(10);
+    let psi = simulation.wave_function().as_1d().unwrap().clone();
+
+    let path = temp_path("checkpoint.qpc");
+    let checkpoint = simulation.checkpoint();
+    assert_eq!((checkpoint.step, checkpoint.time), (10, simulation.time()));
+    checkpoint.save(&path).unwrap();
+    let loaded = Checkpoint::load(&path).unwrap();
+    assert_eq!(loaded, checkpoint);
+    assert_eq!(loaded.psi_1d(), psi);
+
+    // resuming continues exactly where the run stopped
+    let mut resumed = Simulation::from_checkpoint(&loaded).unwrap();
+    simulation.run(10);
+    resumed.run(10);
+    assert_eq!(resumed.step(), 20);
+    assert_eq!(resumed.wave_function(), simulation.wave_function());
+
+    // cut off, or not a checkpoint at all
+    let bytes = std::fs::read(&path).unwrap();
+    std::fs::write(&path, &bytes[..bytes.len() - 8]).unwrap();
+    assert!(Checkpoint::load(&path).is_err());
+    std::fs::write(&path, b"not a checkpoint").unwrap();
+    assert!(Checkpoint::load(&path).is_err());
+    assert!(Checkpoint::load(temp_path("missing.qpc")).is_err());
+    std::fs::remove_file(&path).unwrap();
+
+    // saving goes through a temporary file of its own, which doesn't touch any other file
+    let other = temp_path("checkpoint.tmp");
+    std::fs::write(&other, b"something else").unwrap();
+    checkpoint.save(temp_path("checkpoint.chk")).unwrap();
+    assert_eq!(std::fs::read(&other).unwrap(), b"something else");
+    checkpoint.save(&other).unwrap();
+    assert_eq!(
+        Checkpoint::load(temp_path("checkpoint.chk")).unwrap(),
+        checkpoint
+    );
+    assert_eq!(Checkpoint::load(&other).unwrap(), checkpoint);
+    assert!(!temp_path("checkpoint.chk.tmp").exists() && !temp_path("checkpoint.tmp.tmp").exists());
+    std::fs::remove_file(temp_path("checkpoint.chk")).unwrap();
+    std::fs::remove_file(other).unwrap();
+}
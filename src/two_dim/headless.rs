@@ -0,0 +1,136 @@
+use std::io::Error;
+
+use nalgebra::DMatrix;
+
+use super::iteration::{hamiltonian, potential_at, potential_matrix};
+use crate::{
+    complex::{Complex, Real},
+    export::{Exporter, Frame},
+    quadrature::simpson,
+    scenario::Scenario,
+    simulation::Simulation,
+    Config, Precision,
+};
+
+// Runs the simulation without bevy for the number of steps given by the config,
+// printing the observables every interval steps (and after the last one).
+pub fn run(cfg: &Config) -> Result<(), Error> {
+    match cfg.precision() {
+        Precision::Double => evolve::<f64>(cfg),
+        Precision::Single => evolve::<f32>(cfg),
+    }
+}
+
+// the time evolution itself, stepping psi in T but measuring it in f64
+fn evolve<T: Real>(cfg: &Config) -> Result<(), Error> {
+    // a checkpoint picks up at the step it was saved at, so the same number of
+    // steps finishes the run it came from
+    let mut simulation = match cfg.resume() {
+        Some(checkpoint) => Simulation::<T>::from_checkpoint(checkpoint)?,
+        None => Simulation::<T>::new(cfg.scenario().clone(), 2)?,
+    };
+    let s = cfg.scenario();
+    // the grid is the same along x and z
+    let x = s.grid.points();
+    let potential = potential_matrix(s);
+    let steps = cfg.steps(s.time.dt);
+    let psi = |simulation: &Simulation<T>| {
+        // the simulation is always two dimensional here
+        let psi = simulation.wave_function().cast::<f64>();
+        psi.as_2d().unwrap().clone()
+    };
+
+    print!(
+        "{:>10} {:>12} {:>12} {:>12} {:>12} {:>12}",
+        "step", "time", "norm", "<x>", "<z>", "energy"
+    );
+    // with an absorbing boundary, the probability lost at the edges (since the start or
+    // the checkpoint) is shown as well
+    let initial_norm = s
+        .boundary
+        .absorbing
+        .is_some()
+        .then(|| integrate_2d(&psi(&simulation).map(|p| p.abs_squared()), s));
+    if initial_norm.is_some() {
+        print!(" {:>12}", "absorbed");
+    }
+    println!();
+
+    let start = simulation.step();
+    print_observables(start, &x, &psi(&simulation), &potential, initial_norm, s);
+    export(cfg.exporter(), start, steps, &x, &simulation)?;
+    while simulation.step() < steps {
+        simulation.advance();
+        let step = simulation.step();
+        if step % cfg.interval() == 0 || step == steps {
+            print_observables(step, &x, &psi(&simulation), &potential, initial_norm, s);
+            if let Some(path) = cfg.checkpoint() {
+                simulation.checkpoint().save(path)?;
+            }
+        }
+        export(cfg.exporter(), step, steps, &x, &simulation)?;
+    }
+    Ok(())
+}
+
+// writes the frame after step if the exporter asks for it
+fn export<T: Real>(
+    exporter: Option<&Exporter>,
+    step: usize,
+    last: usize,
+    x: &[f64],
+    simulation: &Simulation<T>,
+) -> Result<(), Error> {
+    let Some(exporter) = exporter.filter(|e| e.wants(step, last)) else {
+        return Ok(());
+    };
+    let psi = simulation.wave_function().cast::<f64>();
+    let frame = Frame::from_2d(step, simulation.time(), x, x, psi.as_2d().unwrap());
+    exporter.write(&frame)?;
+    Ok(())
+}
+
+fn print_observables(
+    step: usize,
+    x: &[f64],
+    psi: &DMatrix<Complex>,
+    potential: &DMatrix<Complex>,
+    initial_norm: Option<f64>,
+    s: &Scenario,
+) {
+    let prob = psi.map(|p| p.abs_squared());
+    let norm = integrate_2d(&prob, s);
+    let x_mean = integrate_2d(
+        &DMatrix::from_fn(prob.nrows(), prob.ncols(), |i, j| x[i] * prob[(i, j)]),
+        s,
+    ) / norm;
+    let z_mean = integrate_2d(
+        &DMatrix::from_fn(prob.nrows(), prob.ncols(), |i, j| x[j] * prob[(i, j)]),
+        s,
+    ) / norm;
+
+    let time = step as f64 * s.time.dt;
+    let h_psi = hamiltonian(psi, &potential_at(potential, time, s), s);
+    let energy = integrate_2d(
+        &psi.zip_map(&h_psi, |p, h_p| (p.complex_conjugate() * h_p).real()),
+        s,
+    ) / norm;
+
+    print!(
+        "{:>10} {:>12.6} {:>12.6} {:>12.6} {:>12.6} {:>12.6}",
+        step, time, norm, x_mean, z_mean, energy
+    );
+    if let Some(initial_norm) = initial_norm {
+        print!(" {:>12.6}", initial_norm - norm);
+    }
+    println!();
+}
+
+// double integral over the whole grid, first along z and then along x
+fn integrate_2d(values: &DMatrix<f64>, s: &Scenario) -> f64 {
+    let rows = values
+        .row_iter()
+        .map(|row| simpson(&row.iter().copied().collect::<Vec<f64>>(), s.grid.spacing))
+        .collect::<Vec<f64>>();
+    simpson(&rows, s.grid.spacing)
+}
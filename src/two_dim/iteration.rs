@@ -0,0 +1,140 @@
+use std::borrow::Cow;
+
+use super::v;
+use crate::{
+    complex::{Complex, Real},
+    potential::TimeDependent,
+    scenario::Scenario,
+    utils::{for_each_chunk, Rk4Workspace},
+};
+use nalgebra::DMatrix;
+use num_traits::Zero;
+
+// The wave function is stored as a matrix where the row index runs along x
+// and the column index along z, matching the layout of the grid from wave().
+// The potential is sampled once with potential_matrix, and the driving terms are added at t.
+// Only the tests still step by returning a new matrix, everything else uses rk4_step.
+#[cfg(test)]
+pub fn rk4_iter_dt<T: Real>(
+    psi0: &DMatrix<Complex<T>>,
+    potential: &DMatrix<Complex<T>>,
+    t: f64,
+    s: &Scenario,
+) -> DMatrix<Complex<T>> {
+    let mut psi = psi0.clone();
+    rk4_step(&mut psi, potential, t, s, &mut Rk4Workspace::default());
+    psi
+}
+
+// rk4_iter_dt in place, which doesn't allocate once the workspace has been used for a step
+pub fn rk4_step<T: Real>(
+    psi: &mut DMatrix<Complex<T>>,
+    potential: &DMatrix<Complex<T>>,
+    t: f64,
+    s: &Scenario,
+    workspace: &mut Rk4Workspace<T>,
+) {
+    let stencil = Stencil::new(potential, s.is_driven(), s);
+    let factor = Complex::new(T::zero(), T::of(-s.time.dt / s.constants.h_bar()));
+    workspace.step(psi.as_mut_slice(), t, s.time.dt, factor, |f, index, t| {
+        stencil.apply(f, index, t)
+    });
+}
+
+// Applies H = -hbar^2/2m * (d^2/dx^2 + d^2/dz^2) + V to f using the five point stencil
+// f(x-dl) + f(x+dl) + f(z-dl) + f(z+dl) - 4f(x, z) for the laplacian.
+// Just like in one dimension, f is taken to be zero outside of the grid,
+// or wraps around to the opposite edge with periodic boundaries.
+pub fn hamiltonian<T: Real>(
+    f: &DMatrix<Complex<T>>,
+    potential: &DMatrix<Complex<T>>,
+    s: &Scenario,
+) -> DMatrix<Complex<T>> {
+    let stencil = Stencil::new(potential, false, s);
+    let mut res = DMatrix::from_element(f.nrows(), f.ncols(), Complex::zero());
+    for_each_chunk(res.as_mut_slice(), |start, chunk| {
+        for (offset, value) in chunk.iter_mut().enumerate() {
+            *value = stencil.apply(f.as_slice(), start + offset, 0.);
+        }
+    });
+    res
+}
+
+// H at a single point, working on the values of the matrix in column major order, i.e. the
+// index runs along x first. That way the grid can be split into chunks of any size.
+struct Stencil<'a, T: Real> {
+    // -hbar^2/(2m*dl^2)
+    kinetic: T,
+    potential: Option<&'a [Complex<T>]>,
+    // whether the driving terms still have to be added to the potential
+    driven: bool,
+    rows: usize,
+    cols: usize,
+    s: &'a Scenario,
+}
+impl<'a, T: Real> Stencil<'a, T> {
+    fn new(potential: &'a DMatrix<Complex<T>>, driven: bool, s: &'a Scenario) -> Self {
+        let (h_bar, m, dl) = (s.constants.h_bar(), s.constants.m, s.grid.spacing);
+        Self {
+            kinetic: T::of(-(h_bar.powi(2) / (2. * m)) / dl.powi(2)),
+            potential: s.has_potential().then_some(potential.as_slice()),
+            driven,
+            rows: potential.nrows(),
+            cols: potential.ncols(),
+            s,
+        }
+    }
+
+    // (H f) at the given index into the values of the matrix, at time t
+    fn apply(&self, f: &[Complex<T>], index: usize, t: f64) -> Complex<T> {
+        let (rows, cols) = (self.rows as isize, self.cols as isize);
+        let at = |i: isize, j: isize| {
+            if self.s.boundary.periodic {
+                f[(i.rem_euclid(rows) + j.rem_euclid(cols) * rows) as usize]
+            } else if i < 0 || j < 0 || i >= rows || j >= cols {
+                Complex::zero()
+            } else {
+                f[(i + j * rows) as usize]
+            }
+        };
+
+        let (i, j) = ((index % self.rows) as isize, (index / self.rows) as isize);
+        let laplace =
+            at(i - 1, j) + at(i + 1, j) + at(i, j - 1) + at(i, j + 1) - f[index] * T::of(4.);
+        let mut res = laplace * self.kinetic;
+        if let Some(potential) = self.potential {
+            let mut v = potential[index];
+            if self.driven {
+                let (x, z) = (self.s.grid.point(i as usize), self.s.grid.point(j as usize));
+                v += T::of(self.s.potential.value_at_2d(x, z, t));
+            }
+            res += v * f[index];
+        }
+        res
+    }
+}
+
+// The potential sampled at every point of the grid, in the same layout as the wave function
+pub fn potential_matrix(s: &Scenario) -> DMatrix<Complex> {
+    let points = s.grid.points();
+    DMatrix::from_fn(points.len(), points.len(), |i, j| {
+        v(points[i], points[j], s)
+    })
+}
+
+// The sampled potential together with the driving terms at time t
+pub fn potential_at<'a, T: Real>(
+    potential: &'a DMatrix<Complex<T>>,
+    t: f64,
+    s: &Scenario,
+) -> Cow<'a, DMatrix<Complex<T>>> {
+    if !s.is_driven() {
+        return Cow::Borrowed(potential);
+    }
+    let points = s.grid.points();
+    Cow::Owned(DMatrix::from_fn(
+        potential.nrows(),
+        potential.ncols(),
+        |i, j| potential[(i, j)] + T::of(s.potential.value_at_2d(points[i], points[j], t)),
+    ))
+}
@@ -1,91 +1,78 @@
-use std::{f64::consts::E, vec};
-
-use nalgebra::DVector;
-use num_traits::Zero;
-
-use crate::{complex::{i, Complex}, utils::simpsons_rule};
-
-const L: f64 = 8.;
-const DL: f64 = 0.1;
-
+use std::io::Error;
+
+#[cfg(any(test, feature = "visual"))]
+use nalgebra::{DMatrix, DVector};
+
+#[cfg(any(test, feature = "visual"))]
+use crate::initial::WaveBuilder;
+use crate::{
+    complex::{i, Complex},
+    potential::Potential,
+    scenario::Scenario,
+    Config,
+};
+
+mod headless;
+pub mod iteration;
+#[cfg(test)]
+mod test;
+#[cfg(feature = "visual")]
 mod visuals;
 
-pub fn run(visual: bool) {
-    if visual {
-        visuals::twoD();
+pub fn run(cfg: &Config) -> Result<(), Error> {
+    #[cfg(feature = "visual")]
+    if cfg.vis() {
+        // fails on an initial state that can't be built before the window opens
+        wave(cfg.scenario())?;
+        visuals::twoD(
+            cfg.scenario().clone(),
+            cfg.visual_exporter(),
+            cfg.resume().cloned(),
+        );
+        return Ok(());
     }
+    headless::run(cfg)
 }
 
-pub fn wave() -> DVector<DVector<(f64, Complex, f64)>> {
-        // the central value of c(k)
-    let k_0: isize = 10;
-    // we cannot integrate form -infty..infty, thus we make the cut-off at this value
-    let k_range: isize = 5; // 10
-    let dk = 0.5; // discretesation of the grid of k
-    let k_values = ((k_0 - k_range)..=(k_0 + k_range))
-        .map(|x| x as f64 * dk)
-        .collect::<Vec<f64>>();
-
-    let delta_k = 5f64; // width of the gaussian
-                        // c(k) = e^(-(k-k_0)/dk)^2
-    let c_k = |k: f64| E.powf(-((k - k_0 as f64) / delta_k).powi(2));
-    // psi_n(x, k) = e^(ikx)
-    let f_k = |u: f64, k: f64| Complex::exp(i() * (k * u));
-
-    // General gaussian
-    // let f = |x: f32, z: f32| {E.powf(-(x.powi(2)+z.powi(2)))};
-    let mut wave: DVector<DVector<(f64, Complex, f64)>> = DVector::from(vec![]);
-
-    for i in -(L/(DL*2.)) as isize..=(L/(DL*2.)) as isize {
-        let x_c = i as f64 *DL;
-        let mut ind_wave = vec![];
-        for j in -(L/(DL*2.)) as isize..=(L/(DL*2.)) as isize {
-            let z_c = j as f64 * DL;
-            let mut res = Complex::zero();
-            for k_x in &k_values {
-                for k_z in &k_values {
-                    res += c_k(*k_x)*c_k(*k_z) * f_k(x_c, *k_x) * f_k(z_c, *k_z);
-                }
-            }
-            ind_wave.push((x_c, res, z_c));
-        }
-        wave = wave.push(ind_wave.into());
+// Sum of all of the terms of the potential described by the scenario,
+// including the imaginary part from an absorbing boundary
+fn v(x: f64, z: f64, s: &Scenario) -> Complex {
+    let mut res = Complex::from_real(s.potential.value_2d(x, z));
+    if let Some(layer) = &s.boundary.absorbing {
+        res -= i() * (layer.value(x, s.grid.length) + layer.value(z, s.grid.length));
     }
-
-    normalize_2d(wave)
+    res
 }
 
-// double integral for 2d
-fn normalize_2d(mut data: DVector<DVector<(f64, Complex, f64)>>) -> DVector<DVector<(f64, Complex, f64)>> {
-    let mut int_vec = Vec::new();
-    // integrate for every vector by itself
-    for v in data.clone().iter() {
-        let data_squared = v.clone().iter().map(|x| x.1.abs_squared()).collect::<Vec<f64>>();
-        int_vec.push(simpsons_rule(data_squared, -L/2., L/2.));
-    }
-
-    // integrate for the combined integral
-    let total_integral = simpsons_rule(int_vec, -L/2., L/2.);
-    println!("{total_integral}");
-    let int_factor = 1./total_integral.sqrt();
-
-    for v in &mut data {
-        v.iter_mut().for_each(|x| x.1 *= int_factor);
-    }
-
-    data
+// (x, psi, z) at every point of the grid, with the rows along x.
+// Only the window and the tests work with the grid, headless runs go through Simulation.
+#[cfg(any(test, feature = "visual"))]
+type WaveGrid = DVector<DVector<(f64, Complex, f64)>>;
+
+// The initial wave function described by the scenario, together with the coordinates of each point
+#[cfg(any(test, feature = "visual"))]
+pub fn wave(s: &Scenario) -> Result<WaveGrid, Error> {
+    let psi = WaveBuilder::from_scenario(s).build_2d(s)?;
+    let points = s.grid.points();
+    Ok(DVector::from(
+        points
+            .iter()
+            .enumerate()
+            .map(|(j, x)| {
+                DVector::from(
+                    points
+                        .iter()
+                        .enumerate()
+                        .map(|(k, z)| (*x, psi[(j, k)], *z))
+                        .collect::<Vec<_>>(),
+                )
+            })
+            .collect::<Vec<_>>(),
+    ))
 }
 
-// pub fn dt(psi0: &DVector<DVector<Complex>>) -> DVector<DVector<Complex>> {
-//     let k1 = d_dt(&psi0);
-//     let k2 = d_dt(&(psi0 + Complex::from_real(0.5) * &k1));
-//     let k3 = d_dt(&(psi0 + Complex::from_real(0.5) * &k2));
-//     let k4 = d_dt(&(psi0 + &k3));
-
-//     psi0 + Complex::from_real(1. / 6.)
-//         * &(k1 + Complex::from_real(2.) * &k2 + Complex::from_real(2.) * &k3 + &k4)
-// }
-
-// fn d_dt(f: &DVector<DVector<Complex>>) -> DVector<DVector<Complex>> {
-//     unimplemented!()
-// }
+// the values of the wave function on the grid, in the layout used by iteration
+#[cfg(any(test, feature = "visual"))]
+fn values(grid: &WaveGrid) -> DMatrix<Complex> {
+    DMatrix::from_fn(grid.len(), grid[0].len(), |i, j| grid[i][j].1)
+}
@@ -0,0 +1,150 @@
+use std::f64::consts::PI;
+
+use super::{
+    iteration::{hamiltonian, potential_at, potential_matrix, rk4_iter_dt},
+    values, wave,
+};
+use crate::{
+    complex::{i, Complex},
+    expression::Expression,
+    initial::WaveBuilder,
+    potential::{Barrier, Harmonic},
+    scenario::{Builtin, Scenario},
+    test::rk4_reference,
+    Propagator, Simulation, WaveFunction,
+};
+use nalgebra::{DMatrix, DVector};
+
+#[test]
+#[allow(non_snake_case)]
+fn twoD_iter_norm() {
+    let s = Scenario::default_for(2);
+    let psi0 = values(&wave(&s).unwrap());
+    let norm = |psi: &DMatrix<Complex>| psi.iter().map(|x| x.abs_squared()).sum::<f64>();
+
+    // RK4 is not exactly unitary, but for small time steps the norm should barely change
+    let potential = potential_matrix(&s);
+    let mut psi = psi0.clone();
+    for _ in 0..50 {
+        psi = rk4_iter_dt(&psi, &potential, 0., &s);
+    }
+    assert!((norm(&psi) - norm(&psi0)).abs() / norm(&psi0) < 1e-6);
+}
+
+#[test]
+fn periodic_stencil() {
+    let mut s = Scenario::default_for(2);
+    s.boundary.periodic = true;
+    let n = s.grid.points().len();
+
+    // plane waves fitting onto the torus are eigenstates of the periodic five point stencil
+    let (j_x, j_z) = (2., 5.);
+    let psi = DMatrix::from_fn(n, n, |a, b| {
+        Complex::exp(i() * (2. * PI * (j_x * a as f64 + j_z * b as f64) / n as f64))
+    });
+    let kinetic = s.constants.h_bar().powi(2) / (s.constants.m * s.grid.spacing.powi(2));
+    let energy =
+        kinetic * (2. - (2. * PI * j_x / n as f64).cos() - (2. * PI * j_z / n as f64).cos());
+
+    let h_psi = hamiltonian(&psi, &potential_matrix(&s), &s);
+    assert!(h_psi
+        .iter()
+        .zip(psi.iter())
+        .all(|(h_p, p)| (*h_p - energy * *p).abs_squared() < 1e-20));
+}
+
+#[test]
+fn product_eigenstate() {
+    let mut s = Scenario::default_for(2);
+    s.potential.enabled = true;
+    s.potential.terms = vec![
+        Builtin::Barrier(Barrier {
+            start: 1.,
+            end: 1.5,
+            height: 3.,
+        })
+        .into(),
+        Builtin::Harmonic(Harmonic { strength: 2. }).into(),
+    ];
+
+    // the product of the eigenstates along x and z is an eigenstate of the five point stencil
+    let psi = WaveBuilder::new().eigenstate(1).build_2d(&s).unwrap();
+    let h_psi = hamiltonian(&psi, &potential_matrix(&s), &s);
+    let energy = psi
+        .iter()
+        .zip(h_psi.iter())
+        .map(|(p, h_p)| (p.complex_conjugate() * *h_p).real())
+        .sum::<f64>()
+        / psi.iter().map(|p| p.abs_squared()).sum::<f64>();
+    assert!(h_psi
+        .iter()
+        .zip(psi.iter())
+        .all(|(h_p, p)| (*h_p - energy * *p).abs_squared() < 1e-16));
+
+    // which doesn't work for a potential that mixes x and z
+    s.set_potential(Expression::parse("x*y").unwrap());
+    assert!(WaveBuilder::new().eigenstate(1).build_2d(&s).is_err());
+    let plane_wave = |x: f64| Complex::exp(i() * x);
+    assert!(WaveBuilder::new()
+        .function(Complex::from_real(1.), plane_wave)
+        .build_2d(&s)
+        .is_err());
+}
+
+#[test]
+fn simulation_api() {
+    let s = Scenario::default_for(2);
+    let mut simulation = Simulation::new(s.clone(), 2).unwrap();
+    simulation.run(3);
+
+    let potential = potential_matrix(&s);
+    let mut psi = values(&wave(&s).unwrap());
+    for step in 0..3 {
+        psi = rk4_iter_dt(&psi, &potential, step as f64 * s.time.dt, &s);
+    }
+    assert_eq!(simulation.wave_function().as_2d(), Some(&psi));
+    let h_psi = simulation
+        .hamiltonian()
+        .apply(simulation.wave_function(), simulation.time())
+        .unwrap();
+    assert_eq!(h_psi.as_2d(), Some(&hamiltonian(&psi, &potential, &s)));
+
+    // a wave function has to fit the grid of the simulation
+    let n = s.grid.points().len();
+    let flat = WaveFunction::from_1d(DVector::from_element(n, Complex::from_real(1.)));
+    let h = simulation.hamiltonian();
+    assert!(h.apply(&flat, 0.).is_err() && h.energy(&flat, 0.).is_err());
+    assert!(Propagator::from_scenario(&s).step(h, &flat, 0.).is_err());
+    assert!(simulation.set_wave_function(flat).is_err());
+    let small = WaveFunction::from_2d(DMatrix::from_element(n - 1, n, Complex::from_real(1.)));
+    assert!(simulation.set_wave_function(small).is_err());
+}
+
+#[test]
+fn driving_along_z() {
+    // a field along z that is switched on over time
+    let mut s = Scenario::default_for(2);
+    s.set_potential(Expression::parse("y*t").unwrap());
+    let points = s.grid.points();
+    let potential = potential_matrix(&s);
+    let t = 0.5;
+    let driven = potential_at(&potential, t, &s);
+    assert!((driven[(3, 7)].real() - points[7] * t).abs() < 1e-12);
+    assert!((driven[(7, 3)].real() - points[3] * t).abs() < 1e-12);
+
+    // the stencil adds the same terms at every stage of RK4
+    let (dt, psi0) = (s.time.dt, values(&wave(&s).unwrap()));
+    let (rows, cols) = psi0.shape();
+    let h = |f: &[Complex], t: f64| {
+        let f = DMatrix::from_column_slice(rows, cols, f);
+        hamiltonian(&f, &potential_at(&potential, t, &s), &s)
+            .as_slice()
+            .to_vec()
+    };
+    let expected = rk4_reference(psi0.as_slice(), t, dt, s.constants.h_bar(), h);
+    let psi = rk4_iter_dt(&psi0, &potential, t, &s);
+    assert!(psi
+        .iter()
+        .zip(expected)
+        .all(|(p, e)| (*p - e).abs() < 1e-12));
+}
@@ -4,17 +4,52 @@ use bevy::{
     a11y::{
         accesskit::{NodeBuilder, Role},
         AccessibilityNode,
-    }, input::keyboard::KeyboardInput, prelude::*};
-use nalgebra::DVector;
+    },
+    input::{keyboard::KeyboardInput, ButtonState},
+    prelude::*,
+};
+use nalgebra::{DMatrix, DVector};
 
-use super::{wave, Complex};
-use super::DL;
+use super::{
+    iteration::{potential_matrix, rk4_step},
+    values, wave, Complex,
+};
+use crate::{
+    checkpoint::Checkpoint,
+    export::{Exporter, Frame},
+    scenario::Scenario,
+    utils::Rk4Workspace,
+};
+
+// run makes sure that the initial state can be built before the app starts
+const CHECKED: &str = "The initial state was checked before the app started";
 
 #[derive(Component)]
 struct Data {
     wave_grid: DVector<DVector<(f64, Complex, f64)>>,
+    // the same values as in wave_grid, in the layout used for iteration
+    raw: DMatrix<Complex>,
+    time_passed: f64,
 }
 
+// the scenario that is being simulated
+#[derive(Resource)]
+struct Settings(Scenario);
+
+// the potential of the scenario on the grid, sampled once at the start
+#[derive(Resource)]
+struct SampledPotential(DMatrix<Complex>);
+
+// where frames are saved to
+#[derive(Resource)]
+struct Export(Exporter);
+
+// the state to start from instead of the initial wave function
+#[derive(Resource)]
+struct Resume(Option<Checkpoint>);
+
+#[derive(Component)]
+struct TimeText;
 #[derive(Component)]
 struct XCoordText;
 #[derive(Component)]
@@ -26,18 +61,24 @@ struct RotationText;
 #[derive(Component)]
 struct FOVTExt;
 
-pub fn twoD() {
+// pressing E saves the current frame with the exporter
+pub fn twoD(scenario: Scenario, exporter: Exporter, resume: Option<Checkpoint>) {
     App::new()
         .add_plugins(DefaultPlugins)
+        .insert_resource(SampledPotential(potential_matrix(&scenario)))
+        .insert_resource(Settings(scenario))
+        .insert_resource(Export(exporter))
+        .insert_resource(Resume(resume))
         .add_systems(Startup, (setup, setup_data))
         .add_systems(Update, render)
-        .add_systems(PostUpdate, (controls, update_text))
+        .add_systems(
+            PostUpdate,
+            (update_wave_function, controls, update_text, export_frame),
+        )
         .run();
 }
 
-fn setup(
-    mut commands: Commands,
-) {
+fn setup(mut commands: Commands) {
     commands.spawn(Camera3dBundle {
         transform: Transform {
             translation: Vec3::new(6.6, 1.6, 0.),
@@ -67,6 +108,7 @@ fn setup(
             ..default()
         })
         .with_children(|parent| {
+            info_text(parent, TimeText);
             info_text(parent, RotationText);
             info_text(parent, FOVTExt);
             info_text(parent, XCoordText);
@@ -85,23 +127,90 @@ fn setup(
     });
 }
 
-fn setup_data(mut commands: Commands) {
-    commands.spawn(Data { wave_grid: wave() });
+fn setup_data(mut commands: Commands, settings: Res<Settings>, resume: Res<Resume>) {
+    let mut wave_grid = wave(&settings.0).expect(CHECKED);
+    let (raw, time_passed) = match &resume.0 {
+        // the coordinates still come from the grid, only the values are replaced
+        Some(checkpoint) => {
+            let raw = checkpoint.psi_2d();
+            for (i, row) in wave_grid.iter_mut().enumerate() {
+                for (j, point) in row.iter_mut().enumerate() {
+                    point.1 = raw[(i, j)];
+                }
+            }
+            (raw, checkpoint.time)
+        }
+        None => (values(&wave_grid), 0.),
+    };
+    commands.spawn(Data {
+        wave_grid,
+        raw,
+        time_passed,
+    });
 }
 
-fn render(mut gizmos: Gizmos, data_query: Query<&Data>)  {
+fn update_wave_function(
+    mut data_query: Query<&mut Data>,
+    settings: Res<Settings>,
+    potential: Res<SampledPotential>,
+    mut workspace: Local<Rk4Workspace>,
+) {
+    let data = &mut *data_query.get_single_mut().unwrap();
+    rk4_step(
+        &mut data.raw,
+        &potential.0,
+        data.time_passed,
+        &settings.0,
+        &mut workspace,
+    );
+
+    // keep the grid used for rendering up to date
+    for (i, row) in data.wave_grid.iter_mut().enumerate() {
+        for (j, point) in row.iter_mut().enumerate() {
+            point.1 = data.raw[(i, j)];
+        }
+    }
+    data.time_passed += settings.0.time.dt;
+}
+
+fn render(mut gizmos: Gizmos, data_query: Query<&Data>, settings: Res<Settings>) {
     let data = data_query.get_single().unwrap();
+    let dl = settings.0.grid.spacing;
     let wave = &data.wave_grid;
-    for i in 0..wave.len() -1{
-        for j in 0..wave.len()-1 {
-            gizmos.ray(Vec3::new(wave[i][j].0 as f32, wave[i][j].1.abs_squared() as f32, wave[i][j].2 as f32), Vec3::new(DL as f32, (wave[i+1][j].1.abs_squared()-wave[i][j].1.abs_squared()) as f32, 0.), Color::GREEN);
-            gizmos.ray(Vec3::new(wave[i][j].0 as f32, wave[i][j].1.abs_squared() as f32, wave[i][j].2 as f32), Vec3::new(0., (wave[i][j+1].1.abs_squared()-wave[i][j].1.abs_squared()) as f32, DL as f32), Color::GREEN);
+    for i in 0..wave.len() - 1 {
+        for j in 0..wave.len() - 1 {
+            gizmos.ray(
+                Vec3::new(
+                    wave[i][j].0 as f32,
+                    wave[i][j].1.abs_squared() as f32,
+                    wave[i][j].2 as f32,
+                ),
+                Vec3::new(
+                    dl as f32,
+                    (wave[i + 1][j].1.abs_squared() - wave[i][j].1.abs_squared()) as f32,
+                    0.,
+                ),
+                Color::GREEN,
+            );
+            gizmos.ray(
+                Vec3::new(
+                    wave[i][j].0 as f32,
+                    wave[i][j].1.abs_squared() as f32,
+                    wave[i][j].2 as f32,
+                ),
+                Vec3::new(
+                    0.,
+                    (wave[i][j + 1].1.abs_squared() - wave[i][j].1.abs_squared()) as f32,
+                    dl as f32,
+                ),
+                Color::GREEN,
+            );
             // // Real Axis
-            // gizmos.ray(Vec3::new(wave[i][j].0 as f32, wave[i][j].1.real() as f32, wave[i][j].2 as f32), Vec3::new(DL as f32, (wave[i+1][j].1.real()-wave[i][j].1.real()) as f32, 0.), Color::RED);
-            // gizmos.ray(Vec3::new(wave[i][j].0 as f32, wave[i][j].1.real() as f32, wave[i][j].2 as f32), Vec3::new(0., (wave[i][j+1].1.real()-wave[i][j].1.real()) as f32, DL as f32), Color::RED);
+            // gizmos.ray(Vec3::new(wave[i][j].0 as f32, wave[i][j].1.real() as f32, wave[i][j].2 as f32), Vec3::new(dl as f32, (wave[i+1][j].1.real()-wave[i][j].1.real()) as f32, 0.), Color::RED);
+            // gizmos.ray(Vec3::new(wave[i][j].0 as f32, wave[i][j].1.real() as f32, wave[i][j].2 as f32), Vec3::new(0., (wave[i][j+1].1.real()-wave[i][j].1.real()) as f32, dl as f32), Color::RED);
             // // Imag Axis
-            // gizmos.ray(Vec3::new(wave[i][j].0 as f32, wave[i][j].1.imag() as f32, wave[i][j].2 as f32), Vec3::new(DL as f32, (wave[i+1][j].1.imag()-wave[i][j].1.imag()) as f32, 0.), Color::BLUE);
-            // gizmos.ray(Vec3::new(wave[i][j].0 as f32, wave[i][j].1.imag() as f32, wave[i][j].2 as f32), Vec3::new(0., (wave[i][j+1].1.imag()-wave[i][j].1.imag()) as f32, DL as f32), Color::BLUE);
+            // gizmos.ray(Vec3::new(wave[i][j].0 as f32, wave[i][j].1.imag() as f32, wave[i][j].2 as f32), Vec3::new(dl as f32, (wave[i+1][j].1.imag()-wave[i][j].1.imag()) as f32, 0.), Color::BLUE);
+            // gizmos.ray(Vec3::new(wave[i][j].0 as f32, wave[i][j].1.imag() as f32, wave[i][j].2 as f32), Vec3::new(0., (wave[i][j+1].1.imag()-wave[i][j].1.imag()) as f32, dl as f32), Color::BLUE);
         }
     }
 }
@@ -136,6 +245,27 @@ fn info_text(parent: &mut ChildBuilder, identifier: impl Component) {
         });
 }
 
+fn export_frame(
+    mut key_evs: EventReader<KeyboardInput>,
+    data_query: Query<&Data>,
+    settings: Res<Settings>,
+    export: Res<Export>,
+) {
+    let s = &settings.0;
+    for e in key_evs.read() {
+        if e.key_code == KeyCode::KeyE && e.state == ButtonState::Pressed {
+            let data = data_query.get_single().unwrap();
+            let step = (data.time_passed / s.time.dt).round() as usize;
+            let points = s.grid.points();
+            let frame = Frame::from_2d(step, data.time_passed, &points, &points, &data.raw);
+            match export.0.write_numbered(&frame) {
+                Ok(path) => println!("Saved frame to {}", path.display()),
+                Err(e) => eprintln!("{e}"),
+            }
+        }
+    }
+}
+
 fn controls(
     mut key_evs: EventReader<KeyboardInput>,
     mut projection_query: Query<&mut Projection, With<Camera3d>>,
@@ -192,7 +322,9 @@ fn controls(
     }
 }
 
+#[allow(clippy::type_complexity)]
 fn update_text(
+    data_query: Query<&Data>,
     mut projection_query: Query<&mut Projection, With<Camera3d>>,
     transform_query: Query<&Transform, With<Camera3d>>,
     mut text_set: ParamSet<(
@@ -201,6 +333,7 @@ fn update_text(
         Query<&mut Text, With<ZCoordText>>,
         Query<&mut Text, With<RotationText>>,
         Query<&mut Text, With<FOVTExt>>,
+        Query<&mut Text, With<TimeText>>,
     )>,
 ) {
     let Projection::Perspective(persp) = projection_query.single_mut().into_inner() else {
@@ -208,6 +341,10 @@ fn update_text(
     };
     let transform = transform_query.get_single().unwrap();
 
+    let data = data_query.get_single().unwrap();
+    for mut time_text in &mut text_set.p5() {
+        time_text.sections[0].value = format!("Time [t.u.]: {}", data.time_passed as f32);
+    }
     for mut rotation_text in &mut text_set.p3() {
         rotation_text.sections[0].value = format!("Rotation: {}", transform.rotation);
     }
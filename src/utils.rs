@@ -1,16 +1,172 @@
-// assumes that the inputted data-points are equally spaced in terms of the independant variable
-// and that the data starts at "start" and ends at "stop"
-pub fn simpsons_rule(data: Vec<f64>, lower_bound: f64, upper_bound: f64) -> f64 {
-    let mut sum: f64 = (1..data.len())
-        .map(|i| {
-            if i % 3 == 0 {
-                2. * data[i]
-            } else {
-                3. * data[i]
-            }
-        })
-        .sum();
-    sum += data[0] + data.last().unwrap();
-
-    sum * 3. * ((upper_bound - lower_bound) / data.len() as f64) / 8.
+use num_traits::{One, Zero};
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+
+use crate::complex::{Complex, Real};
+
+// how many grid points are handed to a thread at a time
+const CHUNK: usize = 4096;
+
+// Calls f(start, chunk) for consecutive chunks of values, where start is the index of the first
+// value in the chunk. With the parallel feature the chunks are spread over a thread pool,
+// without it they are done one after another.
+pub fn for_each_chunk<A: Send>(values: &mut [A], f: impl Fn(usize, &mut [A]) + Sync) {
+    #[cfg(feature = "parallel")]
+    let chunks = values.par_chunks_mut(CHUNK);
+    #[cfg(not(feature = "parallel"))]
+    let chunks = values.chunks_mut(CHUNK);
+    chunks
+        .enumerate()
+        .for_each(|(n, chunk)| f(n * CHUNK, chunk));
+}
+
+// for_each_chunk over two slices of the same length at once
+pub fn for_each_chunk_pair<A: Send, B: Send>(
+    a: &mut [A],
+    b: &mut [B],
+    f: impl Fn(usize, &mut [A], &mut [B]) + Sync,
+) {
+    #[cfg(feature = "parallel")]
+    let chunks = a.par_chunks_mut(CHUNK).zip(b.par_chunks_mut(CHUNK));
+    #[cfg(not(feature = "parallel"))]
+    let chunks = a.chunks_mut(CHUNK).zip(b.chunks_mut(CHUNK));
+    chunks
+        .enumerate()
+        .for_each(|(n, (a, b))| f(n * CHUNK, a, b));
+}
+
+// The buffers RK4 needs on top of psi, kept between steps so that stepping doesn't allocate.
+// They are sized on the first step, and again whenever the length of psi changes.
+#[derive(Debug, Clone, Default)]
+pub struct Rk4Workspace<T: Real = f64> {
+    input: Vec<Complex<T>>,
+    next: Vec<Complex<T>>,
+    sum: Vec<Complex<T>>,
+}
+impl<T: Real> Rk4Workspace<T> {
+    // Takes psi from t to t + dt in place, where h(f, j, t) gives (H f)_j at time t.
+    // Every stage k = factor * H f is added to the sum and to the input of the next stage
+    // in the same pass over the grid, so k itself is never stored. The factor is dt/(i*hbar)
+    // for the Schrödinger equation, or -dt/hbar in imaginary time.
+    pub fn step(
+        &mut self,
+        psi: &mut [Complex<T>],
+        t: f64,
+        dt: f64,
+        factor: Complex<T>,
+        h: impl Fn(&[Complex<T>], usize, f64) -> Complex<T> + Sync,
+    ) {
+        for buffer in [&mut self.input, &mut self.next, &mut self.sum] {
+            buffer.resize(psi.len(), Complex::zero());
+        }
+        self.sum.copy_from_slice(psi);
+        // the time of each stage, its weight in the sum and how far ahead the next stage is
+        let stages = [
+            (t, 1. / 6., 0.5),
+            (t + dt / 2., 1. / 3., 0.5),
+            (t + dt / 2., 1. / 3., 1.),
+            (t + dt, 1. / 6., 0.),
+        ];
+
+        let start = &*psi;
+        for (stage, (time, weight, ahead)) in stages.into_iter().enumerate() {
+            let (weight, ahead) = (T::of(weight), T::of(ahead));
+            let input = if stage == 0 { start } else { &self.input };
+            for_each_chunk_pair(&mut self.sum, &mut self.next, |first, sum, next| {
+                for (offset, (sum, next)) in sum.iter_mut().zip(next).enumerate() {
+                    let j = first + offset;
+                    let k = factor * h(input, j, time);
+                    *sum += k * weight;
+                    *next = start[j] + k * ahead;
+                }
+            });
+            std::mem::swap(&mut self.input, &mut self.next);
+        }
+        psi.copy_from_slice(&self.sum);
+    }
+}
+
+// Solves the tridiagonal system A*x = rhs with the Thomas algorithm, where "sub", "diag" and
+// "sup" are the sub-, main and super diagonals of A. There is no pivoting, which never runs into
+// a zero pivot as long as the Hermitian part of A is positive definite. That holds for the
+// Crank-Nicolson matrices 1 + i*dt/(2*hbar)*H with any dt and potential, but not for the shifted
+// H - E of inverse iteration, so a pivot that comes out as exactly zero is replaced by a tiny one.
+pub fn solve_tridiagonal<T: Real>(
+    sub: &[Complex<T>],
+    diag: &[Complex<T>],
+    sup: &[Complex<T>],
+    rhs: &[Complex<T>],
+) -> Vec<Complex<T>> {
+    let n = diag.len();
+    let mut c_prime = vec![Complex::zero(); n];
+    let mut d_prime = vec![Complex::zero(); n];
+    let tiny = T::epsilon() * diag.iter().map(|d| d.abs()).fold(T::one(), T::max);
+    let pivot = |p: Complex<T>| {
+        if p.is_zero() {
+            Complex::from_real(tiny)
+        } else {
+            p
+        }
+    };
+
+    // forward sweep
+    let first = pivot(diag[0]);
+    c_prime[0] = if n > 1 {
+        sup[0] / first
+    } else {
+        Complex::zero()
+    };
+    d_prime[0] = rhs[0] / first;
+    for i in 1..n {
+        let denominator = pivot(diag[i] - sub[i - 1] * c_prime[i - 1]);
+        if i < n - 1 {
+            c_prime[i] = sup[i] / denominator;
+        }
+        d_prime[i] = (rhs[i] - sub[i - 1] * d_prime[i - 1]) / denominator;
+    }
+
+    // back substitution
+    let mut res = d_prime;
+    for i in (0..n - 1).rev() {
+        res[i] = res[i] - c_prime[i] * res[i + 1];
+    }
+    res
+}
+
+// Solves A*x = rhs for a tridiagonal A with additional entries in its corners, i.e.
+// A[0][n-1] = upper_corner and A[n-1][0] = lower_corner, which is what periodic boundaries
+// lead to. A is split into a tridiagonal part and a rank one correction, so the system
+// can be solved with two ordinary tridiagonal solves (Sherman-Morrison).
+pub fn solve_cyclic_tridiagonal<T: Real>(
+    sub: &[Complex<T>],
+    diag: &[Complex<T>],
+    sup: &[Complex<T>],
+    upper_corner: Complex<T>,
+    lower_corner: Complex<T>,
+    rhs: &[Complex<T>],
+) -> Vec<Complex<T>> {
+    let n = diag.len();
+    // any gamma but zero works, -diag[0] keeps the modified diagonal from cancelling out
+    let gamma = if diag[0].is_zero() {
+        -Complex::one()
+    } else {
+        -diag[0]
+    };
+
+    let mut modified = diag.to_vec();
+    modified[0] = diag[0] - gamma;
+    modified[n - 1] = diag[n - 1] - lower_corner * upper_corner / gamma;
+    let x = solve_tridiagonal(sub, &modified, sup, rhs);
+
+    let mut u = vec![Complex::zero(); n];
+    u[0] = gamma;
+    u[n - 1] = lower_corner;
+    let z = solve_tridiagonal(sub, &modified, sup, &u);
+
+    let factor = (x[0] + upper_corner * x[n - 1] / gamma)
+        / (Complex::one() + z[0] + upper_corner * z[n - 1] / gamma);
+    x.iter()
+        .zip(z.iter())
+        .map(|(x, z)| *x - factor * *z)
+        .collect()
 }